@@ -61,3 +61,216 @@ pub struct GpuLimits {
     pub clock_step: u64,
     pub memory_control_capable: bool,
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct ProfileInfo {
+    pub id: Option<u64>,
+    pub filename: String,
+    pub name: String,
+    pub driver: Option<crate::persist::DriverJson>,
+    pub persistent: bool,
+    pub active: bool,
+    /// Filename of the profile this one inherits from
+    pub parent: Option<String>,
+    /// Last modified time, in seconds since the UNIX epoch
+    pub modified: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ProfileLayers {
+    /// Filename of the profile the current settings inherit from
+    pub parent: Option<String>,
+    /// Settings which differ from the parent (e.g. `gpu.fast_ppt` or `cpus.0.online`)
+    pub overridden: Vec<String>,
+    /// Settings which are the same as the parent's
+    pub inherited: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Time of the change, in seconds since the UNIX epoch
+    pub time: u64,
+    /// Settings which changed compared to the previous entry
+    pub changed: Vec<String>,
+    /// Whether this entry matches the current settings
+    pub current: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SettingsHistory {
+    pub position: usize,
+    pub can_undo: bool,
+    pub can_redo: bool,
+    /// Oldest entry first
+    pub entries: Vec<HistoryEntry>,
+}
+
+/// A setting which is outside of what the device's driver allows
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LimitViolation {
+    /// Path of the setting, e.g. `gpu.fast_ppt` or `cpus.0.clock_limits.max`
+    pub setting: String,
+    pub value: String,
+    /// Description of the allowed values
+    pub allowed: String,
+    pub action: LimitAction,
+    /// Value the setting was changed to, if it was clamped
+    pub new_value: Option<String>,
+}
+
+/// What was done about a setting which is outside of the device's limits
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitAction {
+    /// Changed to the closest allowed value
+    Clamped,
+    /// Unset, since there is no closest allowed value
+    Dropped,
+    /// Left as-is
+    Kept,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ImportedProfile {
+    pub filename: String,
+    pub name: String,
+    /// Settings which were changed to fit within this device's limits
+    pub clamped: Vec<LimitViolation>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ImportResult {
+    pub imported: Vec<ImportedProfile>,
+    /// Whether the profiles were exported from the same kind of device
+    pub fingerprint_match: bool,
+    pub warnings: Vec<String>,
+}
+
+/// Reading of a hwmon temperature sensor
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TemperatureReading {
+    /// Name of the hwmon device, e.g. `amdgpu` or `k10temp`
+    pub sensor: String,
+    /// Sensor input, e.g. `temp1`
+    pub input: String,
+    pub label: Option<String>,
+    /// Degrees Celsius
+    pub temperature: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ThermalStatus {
+    pub config: crate::persist::ThermalGovernorJson,
+    /// How far PPT and clocks are lowered, from 0 (not at all) to `max_level`
+    pub level: u8,
+    pub max_level: u8,
+    pub throttling: bool,
+    pub apu_temperature: Option<f64>,
+    pub battery_temperature: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkerStatus {
+    Running,
+    /// Returned by itself
+    Stopped,
+    Crashed,
+    /// Crashed, and waiting to be started again
+    Restarting,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WorkerHealth {
+    pub name: String,
+    pub status: WorkerStatus,
+    pub restarts: u32,
+    /// Panic message from the last crash
+    pub last_error: Option<String>,
+}
+
+/// Error from applying settings, kept for the front-end
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApplyError {
+    /// What was being applied, e.g. `on_set` or `on_power_event`
+    pub source: String,
+    pub setting: crate::settings::SettingVariant,
+    pub kind: crate::settings::SettingErrorKind,
+    /// The sysfs path or parameter which the error is about
+    pub target: Option<String>,
+    pub msg: String,
+    /// Seconds since the Unix epoch
+    pub time: u64,
+}
+
+/// A value read back from the hardware, next to the value the settings want
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ActualValue<T> {
+    /// `None` when the setting is left to the system
+    pub desired: Option<T>,
+    /// `None` when the hardware value can't be read
+    pub actual: Option<T>,
+    /// Something other than PowerTools changed the hardware value
+    pub drift: bool,
+}
+
+impl<T: PartialEq> ActualValue<T> {
+    pub fn new(desired: Option<T>, actual: Option<T>) -> Self {
+        let drift = matches!((&desired, &actual), (Some(d), Some(a)) if d != a);
+        Self {
+            desired,
+            actual,
+            drift,
+        }
+    }
+}
+
+/// GPU values the hardware is using, in the same units as the GPU getters
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GpuActual {
+    pub fast_ppt: ActualValue<u64>,
+    pub slow_ppt: ActualValue<u64>,
+    pub clock_min: ActualValue<u64>,
+    pub clock_max: ActualValue<u64>,
+    /// `power_dpm_force_performance_level`, for drivers which need to control it
+    pub performance_level: ActualValue<String>,
+}
+
+impl GpuActual {
+    pub fn new(
+        desired_ppt: (Option<u64>, Option<u64>),
+        desired_clocks: Option<&crate::settings::MinMax<u64>>,
+        actual_ppt: (Option<u64>, Option<u64>),
+        actual_clocks: Option<crate::settings::MinMax<u64>>,
+    ) -> Self {
+        Self {
+            fast_ppt: ActualValue::new(desired_ppt.0, actual_ppt.0),
+            slow_ppt: ActualValue::new(desired_ppt.1, actual_ppt.1),
+            clock_min: ActualValue::new(
+                desired_clocks.and_then(|c| c.min),
+                actual_clocks.as_ref().and_then(|c| c.min),
+            ),
+            clock_max: ActualValue::new(
+                desired_clocks.and_then(|c| c.max),
+                actual_clocks.as_ref().and_then(|c| c.max),
+            ),
+            performance_level: ActualValue::new(None, None),
+        }
+    }
+}
+
+/// CPU values the hardware is using, in the same units as the CPU getters
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CpuActual {
+    pub online: ActualValue<bool>,
+    pub governor: ActualValue<String>,
+    pub clock_min: ActualValue<u64>,
+    pub clock_max: ActualValue<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CpusActual {
+    pub smt: ActualValue<bool>,
+    pub cpus: Vec<CpuActual>,
+}
+
@@ -3,8 +3,7 @@ use std::sync::{Arc, Mutex};
 use usdpl_back::core::serdes::Primitive;
 use usdpl_back::AsyncCallable;
 
-use crate::settings::{MinMax, SettingError, SettingVariant};
-//use crate::utility::{unwrap_lock, unwrap_maybe_fatal};
+use crate::settings::{MinMax, SettingError, SettingErrorKind, SettingVariant};
 use super::handler::{ApiMessage, CpuMessage};
 use super::utility::map_optional;
 
@@ -16,6 +15,8 @@ pub fn max_cpus(_: super::ApiParameterType) -> super::ApiParameterType {
             .ok_or_else(|| SettingError {
                 msg: "Failed to parse CPU count".to_owned(),
                 setting: SettingVariant::Cpu,
+                kind: SettingErrorKind::Parse,
+                target: Some("/sys/devices/system/cpu/present".to_owned()),
             }),
     )
 }
@@ -359,3 +360,29 @@ pub fn get_cpu_governors(
         output
     }
 }
+
+/// Generate get actual hardware values web method
+pub fn get_actual(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move || {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |value: super::CpusActual| {
+                tx.send(value).expect("get_actual callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::Cpu(CpuMessage::GetActual(Box::new(callback))))
+                .expect("get_actual send failed");
+            rx.recv().expect("get_actual callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIshGetter {
+        set_get: getter,
+        trans_getter: |value: super::CpusActual| {
+            vec![Primitive::Json(serde_json::to_string(&value).unwrap())]
+        },
+    }
+}
@@ -3,8 +3,7 @@ use std::sync::{Arc, Mutex};
 use usdpl_back::core::serdes::Primitive;
 use usdpl_back::AsyncCallable;
 
-//use crate::utility::{unwrap_lock, unwrap_maybe_fatal};
-use super::handler::{ApiMessage, GeneralMessage};
+use super::handler::{ApiMessage, GeneralMessage, HistoryMessage, ProfileMessage};
 
 /// Generate set persistent web method
 pub fn set_persistent(
@@ -210,6 +209,61 @@ pub fn lock_unlock_all(sender: Sender<ApiMessage>) -> impl AsyncCallable {
     }
 }
 
+/// Generate get errors from applying settings web method
+pub fn get_errors(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move || {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |errors: Vec<super::ApplyError>| {
+                tx.send(errors).expect("get_errors callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::GetErrors(Box::new(callback)))
+                .expect("get_errors send failed");
+            rx.recv().expect("get_errors callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIshGetter {
+        set_get: getter,
+        trans_getter: apply_errors_to_primitives,
+    }
+}
+
+/// Generate wait for settings to be applied web method, which returns the errors from applying them
+pub fn wait_for_apply(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move || {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |errors: Vec<super::ApplyError>| {
+                tx.send(errors).expect("wait_for_apply callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::WaitForApply(Box::new(callback)))
+                .expect("wait_for_apply send failed");
+            rx.recv().expect("wait_for_apply callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIshGetter {
+        set_get: getter,
+        trans_getter: apply_errors_to_primitives,
+    }
+}
+
+fn apply_errors_to_primitives(errors: Vec<super::ApplyError>) -> super::ApiParameterType {
+    errors
+        .iter()
+        .filter_map(|e| serde_json::to_string(e).ok().map(Primitive::Json))
+        .collect()
+}
+
 /// Generate get limits web method
 pub fn get_limits(
     sender: Sender<ApiMessage>,
@@ -232,6 +286,271 @@ pub fn get_limits(
     }
 }
 
+/// Generate get settings adjusted to fit within limits web method
+pub fn get_limit_violations(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move || {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |violations: Vec<super::LimitViolation>| {
+                tx.send(violations)
+                    .expect("get_limit_violations callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::GetLimitViolations(Box::new(callback)))
+                .expect("get_limit_violations send failed");
+            rx.recv().expect("get_limit_violations callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIshGetter {
+        set_get: getter,
+        trans_getter: |violations: Vec<super::LimitViolation>| {
+            violations
+                .iter()
+                .filter_map(|v| serde_json::to_string(v).ok().map(Primitive::Json))
+                .collect()
+        },
+    }
+}
+
+/// Generate set power event rules web method
+pub fn set_rules(
+    sender: Sender<ApiMessage>,
+) -> impl Fn(super::ApiParameterType) -> super::ApiParameterType {
+    let sender = Mutex::new(sender); // Sender is not Sync; this is required for safety
+    let setter = move |rules: Vec<crate::persist::RuleJson>| {
+        sender
+            .lock()
+            .unwrap()
+            .send(ApiMessage::SetRules(rules))
+            .expect("set_rules send failed")
+    };
+    move |params_in: super::ApiParameterType| match params_in.first() {
+        Some(Primitive::String(rules)) | Some(Primitive::Json(rules)) => {
+            match serde_json::from_str(rules) {
+                Ok(rules) => {
+                    setter(rules);
+                    vec![true.into()]
+                }
+                Err(e) => vec![format!("set_rules invalid rules: {}", e).into()],
+            }
+        }
+        _ => vec!["set_rules missing parameter".into()],
+    }
+}
+
+/// Generate set enforcement mode web method
+pub fn set_enforce(
+    sender: Sender<ApiMessage>,
+) -> impl Fn(super::ApiParameterType) -> super::ApiParameterType {
+    let sender = Mutex::new(sender); // Sender is not Sync; this is required for safety
+    let setter = move |enforce: bool| {
+        sender
+            .lock()
+            .unwrap()
+            .send(ApiMessage::SetEnforce(enforce))
+            .expect("set_enforce send failed")
+    };
+    move |params_in: super::ApiParameterType| {
+        if let Some(&Primitive::Bool(new_val)) = params_in.first() {
+            setter(new_val);
+            vec![new_val.into()]
+        } else {
+            vec!["set_enforce missing parameter".into()]
+        }
+    }
+}
+
+/// Generate get enforcement mode web method
+pub fn get_enforce(
+    sender: Sender<ApiMessage>,
+) -> impl Fn(super::ApiParameterType) -> super::ApiParameterType {
+    let sender = Mutex::new(sender); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let (tx, rx) = mpsc::channel();
+        let callback =
+            move |value: bool| tx.send(value).expect("get_enforce callback send failed");
+        sender
+            .lock()
+            .unwrap()
+            .send(ApiMessage::GetEnforce(Box::new(callback)))
+            .expect("get_enforce send failed");
+        rx.recv().expect("get_enforce callback recv failed")
+    };
+    move |_: super::ApiParameterType| vec![getter().into()]
+}
+
+/// Generate get power event rules web method
+pub fn get_rules(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move || {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |rules: Vec<crate::persist::RuleJson>| {
+                tx.send(rules).expect("get_rules callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::GetRules(Box::new(callback)))
+                .expect("get_rules send failed");
+            rx.recv().expect("get_rules callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIshGetter {
+        set_get: getter,
+        trans_getter: |rules: Vec<crate::persist::RuleJson>| {
+            vec![Primitive::Json(serde_json::to_string(&rules).unwrap())]
+        },
+    }
+}
+
+/// Generate get profile schedule web method
+pub fn get_schedule(_: super::ApiParameterType) -> super::ApiParameterType {
+    let path = crate::utility::settings_dir().join(crate::consts::SCHEDULE_FILE);
+    let schedule = match crate::persist::ScheduleJson::open(&path) {
+        Ok(x) => x,
+        Err(crate::persist::JsonError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            crate::persist::ScheduleJson::default()
+        }
+        Err(e) => return vec![format!("get_schedule failed to open schedule: {}", e).into()],
+    };
+    vec![Primitive::Json(serde_json::to_string(&schedule).unwrap())]
+}
+
+/// Generate set profile schedule web method
+pub fn set_schedule(
+    wake: Sender<()>,
+) -> impl Fn(super::ApiParameterType) -> super::ApiParameterType {
+    let wake = Mutex::new(wake); // Sender is not Sync; this is required for safety
+    move |params_in: super::ApiParameterType| match params_in.first() {
+        Some(Primitive::String(schedule)) | Some(Primitive::Json(schedule)) => {
+            let schedule: crate::persist::ScheduleJson = match serde_json::from_str(schedule) {
+                Ok(x) => x,
+                Err(e) => return vec![format!("set_schedule invalid schedule: {}", e).into()],
+            };
+            let path = crate::utility::settings_dir().join(crate::consts::SCHEDULE_FILE);
+            if let Err(e) = schedule.save(&path) {
+                return vec![format!("set_schedule failed to save schedule: {}", e).into()];
+            }
+            if let Err(e) = crate::utility::chown_settings_dir() {
+                log::error!("Failed to change config dir permissions: {}", e);
+            }
+            // apply the new schedule right away
+            if wake.lock().unwrap().send(()).is_err() {
+                log::warn!("set_schedule failed to notify schedule worker");
+            }
+            vec![true.into()]
+        }
+        _ => vec!["set_schedule missing parameter".into()],
+    }
+}
+
+/// Generate get app id of running game web method
+pub fn get_running_game(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move || {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |app: Option<u64>| {
+                tx.send(app).expect("get_running_game callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::GetRunningGame(Box::new(callback)))
+                .expect("get_running_game send failed");
+            rx.recv().expect("get_running_game callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIshGetter {
+        set_get: getter,
+        trans_getter: |app: Option<u64>| {
+            vec![app.map(|x| Primitive::String(x.to_string())).unwrap_or(Primitive::Empty)]
+        },
+    }
+}
+
+/// Generate get temperature sensor readings web method
+pub fn get_temperatures(_: super::ApiParameterType) -> super::ApiParameterType {
+    crate::settings::read_temperatures()
+        .iter()
+        .filter_map(|r| serde_json::to_string(r).ok().map(Primitive::Json))
+        .collect()
+}
+
+/// Generate get thermal governor status web method
+pub fn get_thermal_status(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move || {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |status: super::ThermalStatus| {
+                tx.send(status).expect("get_thermal_status callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::GetThermalStatus(Box::new(callback)))
+                .expect("get_thermal_status send failed");
+            rx.recv().expect("get_thermal_status callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIshGetter {
+        set_get: getter,
+        trans_getter: |status: super::ThermalStatus| {
+            vec![Primitive::Json(serde_json::to_string(&status).unwrap())]
+        },
+    }
+}
+
+/// Generate set thermal governor config web method
+pub fn set_thermal_governor(
+    sender: Sender<ApiMessage>,
+) -> impl Fn(super::ApiParameterType) -> super::ApiParameterType {
+    let sender = Mutex::new(sender); // Sender is not Sync; this is required for safety
+    let setter = move |config: crate::persist::ThermalGovernorJson| {
+        sender
+            .lock()
+            .unwrap()
+            .send(ApiMessage::SetThermalConfig(config))
+            .expect("set_thermal_governor send failed")
+    };
+    move |params_in: super::ApiParameterType| match params_in.first() {
+        Some(Primitive::String(config)) | Some(Primitive::Json(config)) => {
+            match serde_json::from_str(config) {
+                Ok(config) => {
+                    setter(config);
+                    vec![true.into()]
+                }
+                Err(e) => vec![format!("set_thermal_governor invalid config: {}", e).into()],
+            }
+        }
+        _ => vec!["set_thermal_governor missing parameter".into()],
+    }
+}
+
+/// Generate get worker thread health web method
+pub fn get_health(
+    health: Arc<Mutex<Vec<super::WorkerHealth>>>,
+) -> impl Fn(super::ApiParameterType) -> super::ApiParameterType {
+    // this doesn't go through the api worker, so it still works when that has crashed
+    move |_: super::ApiParameterType| {
+        health
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|h| serde_json::to_string(h).ok().map(Primitive::Json))
+            .collect()
+    }
+}
+
 /// Generate get current driver name
 pub fn get_provider(sender: Sender<ApiMessage>) -> impl AsyncCallable {
     let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
@@ -375,3 +694,364 @@ fn wait_for_response<T>(sender: &Sender<ApiMessage>, rx: mpsc::Receiver<T>, api_
     sender.send(api_msg).expect(&format!("{} send failed", op));
     rx.recv().expect(&format!("{} callback recv failed", op))
 }
+
+/// Generate list saved profiles web method
+pub fn get_profiles(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move || {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |profiles: Vec<super::ProfileInfo>| {
+                tx.send(profiles).expect("get_profiles callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::Profile(ProfileMessage::List(Box::new(callback))))
+                .expect("get_profiles send failed");
+            rx.recv().expect("get_profiles callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIshGetter {
+        set_get: getter,
+        trans_getter: |profiles: Vec<super::ProfileInfo>| {
+            profiles
+                .iter()
+                .filter_map(|p| serde_json::to_string(p).ok().map(Primitive::Json))
+                .collect()
+        },
+    }
+}
+
+/// Generate rename saved profile web method
+pub fn rename_profile(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move |(id, name): (u64, String)| {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |result: Result<(), String>| {
+                tx.send(result).expect("rename_profile callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::Profile(ProfileMessage::Rename(
+                    id,
+                    name,
+                    Box::new(callback),
+                )))
+                .expect("rename_profile send failed");
+            rx.recv().expect("rename_profile callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIsh {
+        trans_setter: |params: super::ApiParameterType| {
+            let id = profile_id_param(&params, 0, "rename_profile")?;
+            if let Some(Primitive::String(name)) = params.get(1) {
+                Ok((id, name.to_owned()))
+            } else {
+                Err("rename_profile missing name parameter".to_owned())
+            }
+        },
+        set_get: getter,
+        trans_getter: map_profile_result,
+    }
+}
+
+/// Generate copy saved profile to another id web method
+pub fn duplicate_profile(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move |(src, dst): (u64, u64)| {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |result: Result<(), String>| {
+                tx.send(result).expect("duplicate_profile callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::Profile(ProfileMessage::Duplicate(
+                    src,
+                    dst,
+                    Box::new(callback),
+                )))
+                .expect("duplicate_profile send failed");
+            rx.recv().expect("duplicate_profile callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIsh {
+        trans_setter: |params: super::ApiParameterType| {
+            Ok((
+                profile_id_param(&params, 0, "duplicate_profile")?,
+                profile_id_param(&params, 1, "duplicate_profile")?,
+            ))
+        },
+        set_get: getter,
+        trans_getter: map_profile_result,
+    }
+}
+
+/// Generate delete saved profile web method
+pub fn delete_profile(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move |id: u64| {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |result: Result<(), String>| {
+                tx.send(result).expect("delete_profile callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::Profile(ProfileMessage::Delete(
+                    id,
+                    Box::new(callback),
+                )))
+                .expect("delete_profile send failed");
+            rx.recv().expect("delete_profile callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIsh {
+        trans_setter: |params: super::ApiParameterType| profile_id_param(&params, 0, "delete_profile"),
+        set_get: getter,
+        trans_getter: map_profile_result,
+    }
+}
+
+/// Generate set parent of current profile web method
+pub fn set_parent(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move |parent: Option<String>| {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |result: Result<(), String>| {
+                tx.send(result).expect("set_parent callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::Profile(ProfileMessage::SetParent(
+                    parent,
+                    Box::new(callback),
+                )))
+                .expect("set_parent send failed");
+            rx.recv().expect("set_parent callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIsh {
+        trans_setter: |params: super::ApiParameterType| match params.first() {
+            Some(Primitive::String(parent)) => Ok(Some(parent.to_owned())),
+            Some(Primitive::Empty) | None => Ok(None),
+            _ => Err("set_parent invalid parent parameter".to_owned()),
+        },
+        set_get: getter,
+        trans_getter: map_profile_result,
+    }
+}
+
+/// Generate get inherited and overridden settings of current profile web method
+pub fn get_layers(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move || {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |layers: Result<super::ProfileLayers, String>| {
+                tx.send(layers).expect("get_layers callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::Profile(ProfileMessage::GetLayers(Box::new(callback))))
+                .expect("get_layers send failed");
+            rx.recv().expect("get_layers callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIshGetter {
+        set_get: getter,
+        trans_getter: |layers: Result<super::ProfileLayers, String>| match layers {
+            Ok(layers) => vec![Primitive::Json(serde_json::to_string(&layers).unwrap())],
+            Err(e) => {
+                log::warn!("Failed to get profile layers: {}", e);
+                vec![e.into()]
+            }
+        },
+    }
+}
+
+/// Generate export profiles as a bundle web method
+pub fn export_profiles(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move |filenames: Vec<String>| {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |bundle: Result<crate::persist::BundleJson, String>| {
+                tx.send(bundle).expect("export_profiles callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::Profile(ProfileMessage::Export(
+                    filenames,
+                    Box::new(callback),
+                )))
+                .expect("export_profiles send failed");
+            rx.recv().expect("export_profiles callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIsh {
+        trans_setter: |params: super::ApiParameterType| {
+            params
+                .iter()
+                .map(|p| match p {
+                    Primitive::String(filename) => Ok(filename.to_owned()),
+                    _ => Err("export_profiles invalid filename parameter".to_owned()),
+                })
+                .collect()
+        },
+        set_get: getter,
+        trans_getter: |bundle: Result<crate::persist::BundleJson, String>| match bundle {
+            Ok(bundle) => vec![Primitive::Json(serde_json::to_string(&bundle).unwrap())],
+            Err(e) => {
+                log::warn!("Failed to export profiles: {}", e);
+                vec![e.into()]
+            }
+        },
+    }
+}
+
+/// Generate import profiles from a bundle web method
+pub fn import_profiles(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move |(bundle, overwrite): (crate::persist::BundleJson, bool)| {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |result: Result<super::ImportResult, String>| {
+                tx.send(result).expect("import_profiles callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::Profile(ProfileMessage::Import(
+                    bundle,
+                    overwrite,
+                    Box::new(callback),
+                )))
+                .expect("import_profiles send failed");
+            rx.recv().expect("import_profiles callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIsh {
+        trans_setter: |params: super::ApiParameterType| {
+            // existing profiles are only replaced when asked to
+            let overwrite = matches!(params.get(1), Some(Primitive::Bool(true)));
+            match params.first() {
+                Some(Primitive::String(bundle)) | Some(Primitive::Json(bundle)) => {
+                    serde_json::from_str(bundle)
+                        .map(|bundle| (bundle, overwrite))
+                        .map_err(|e| format!("import_profiles invalid bundle: {}", e))
+                }
+                _ => Err("import_profiles missing bundle parameter".to_owned()),
+            }
+        },
+        set_get: getter,
+        trans_getter: |result: Result<super::ImportResult, String>| match result {
+            Ok(result) => vec![Primitive::Json(serde_json::to_string(&result).unwrap())],
+            Err(e) => {
+                log::warn!("Failed to import profiles: {}", e);
+                vec![e.into()]
+            }
+        },
+    }
+}
+
+/// Generate undo last settings change web method
+pub fn undo(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    history_op(sender, HistoryMessage::Undo, "undo")
+}
+
+/// Generate redo last undone settings change web method
+pub fn redo(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    history_op(sender, HistoryMessage::Redo, "redo")
+}
+
+fn history_op(
+    sender: Sender<ApiMessage>,
+    msg: fn(Box<dyn FnOnce(bool) + Send>) -> HistoryMessage,
+    op: &'static str,
+) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move || {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |done: bool| {
+                tx.send(done)
+                    .unwrap_or_else(|_| panic!("{} callback send failed", op))
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::History(msg(Box::new(callback))))
+                .unwrap_or_else(|_| panic!("{} send failed", op));
+            rx.recv().unwrap_or_else(|_| panic!("{} callback recv failed", op))
+        }
+    };
+    super::async_utils::AsyncIshGetter {
+        set_get: getter,
+        trans_getter: |done: bool| vec![done.into()],
+    }
+}
+
+/// Generate get settings change history web method
+pub fn get_history(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move || {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |history: super::SettingsHistory| {
+                tx.send(history).expect("get_history callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::History(HistoryMessage::Get(Box::new(callback))))
+                .expect("get_history send failed");
+            rx.recv().expect("get_history callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIshGetter {
+        set_get: getter,
+        trans_getter: |history: super::SettingsHistory| {
+            vec![Primitive::Json(serde_json::to_string(&history).unwrap())]
+        },
+    }
+}
+
+fn profile_id_param(params: &super::ApiParameterType, index: usize, op: &str) -> Result<u64, String> {
+    match params.get(index) {
+        Some(Primitive::String(id)) => id
+            .parse()
+            .map_err(|e| format!("{} invalid id parameter {}: {}", op, index, e)),
+        Some(Primitive::F64(id)) => Ok(*id as u64),
+        _ => Err(format!("{} missing id parameter {}", op, index)),
+    }
+}
+
+fn map_profile_result(result: Result<(), String>) -> super::ApiParameterType {
+    match result {
+        Ok(()) => vec![true.into()],
+        Err(e) => {
+            log::warn!("Profile operation failed: {}", e);
+            vec![e.into()]
+        }
+    }
+}
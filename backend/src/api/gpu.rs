@@ -5,7 +5,6 @@ use usdpl_back::AsyncCallable;
 
 use super::utility::map_optional;
 use crate::settings::MinMax;
-//use crate::utility::{unwrap_lock, unwrap_maybe_fatal};
 use super::handler::{ApiMessage, GpuMessage};
 
 pub fn set_ppt(
@@ -202,3 +201,29 @@ pub fn get_slow_memory(sender: Sender<ApiMessage>) -> impl AsyncCallable {
         trans_getter: |value: bool| vec![value.into()],
     }
 }
+
+/// Generate get actual hardware values web method
+pub fn get_actual(sender: Sender<ApiMessage>) -> impl AsyncCallable {
+    let sender = Arc::new(Mutex::new(sender)); // Sender is not Sync; this is required for safety
+    let getter = move || {
+        let sender2 = sender.clone();
+        move || {
+            let (tx, rx) = mpsc::channel();
+            let callback = move |value: super::GpuActual| {
+                tx.send(value).expect("get_actual callback send failed")
+            };
+            sender2
+                .lock()
+                .unwrap()
+                .send(ApiMessage::Gpu(GpuMessage::GetActual(Box::new(callback))))
+                .expect("get_actual send failed");
+            rx.recv().expect("get_actual callback recv failed")
+        }
+    };
+    super::async_utils::AsyncIshGetter {
+        set_get: getter,
+        trans_getter: |value: super::GpuActual| {
+            vec![Primitive::Json(serde_json::to_string(&value).unwrap())]
+        },
+    }
+}
@@ -1,11 +1,15 @@
+use std::collections::HashMap;
 use std::fmt::Write;
 use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 use crate::persist::SettingsJson;
 use crate::settings::{
     MinMax, OnPowerEvent, OnResume, OnSet, PowerMode, Settings, TBattery, TCpus, TGeneral, TGpu,
 };
-use crate::utility::unwrap_maybe_fatal;
+
+use super::history::History;
 
 type Callback<T> = Box<dyn FnOnce(T) + Send>;
 
@@ -15,19 +19,38 @@ pub enum ApiMessage {
     Gpu(GpuMessage),
     General(GeneralMessage),
     OnResume,
-    #[allow(dead_code)]
     OnPluggedIn,
-    #[allow(dead_code)]
     OnUnplugged,
-    #[allow(dead_code)]
     OnChargeChange(f64), // battery fill amount: 0 = empty, 1 = full
-    PowerVibeCheck,
+    PowerVibeCheck(bool), // only battery charge, when uevent_worker is sending the power events above
+    CheckTemperatures,
     WaitForEmptyQueue(Callback<()>),
+    /// Called with the errors of the next apply, or of the last one if nothing needs applying
+    WaitForApply(Callback<Vec<super::ApplyError>>),
+    GetErrors(Callback<Vec<super::ApplyError>>),
     LoadSettings(u64, String), // (path, name)
     LoadMainSettings,
     LoadSystemSettings,
     GetLimits(Callback<super::SettingsLimits>),
+    GetLimitViolations(Callback<Vec<super::LimitViolation>>),
+    SetRules(Vec<crate::persist::RuleJson>),
+    GetRules(Callback<Vec<crate::persist::RuleJson>>),
+    SetEnforce(bool),
+    GetEnforce(Callback<bool>),
+    /// Re-apply settings which drifted, if the profile enforces them
+    Enforce,
+    /// Apply the changes of a schedule entry on top of the settings, until another profile is loaded
+    ApplyChanges(crate::persist::PartialSettingsJson),
+    GetThermalStatus(Callback<super::ThermalStatus>),
+    SetThermalConfig(crate::persist::ThermalGovernorJson),
+    RunningGameChanged(Option<u64>), // app id
+    /// Restore the original state once the settings are saved, then stop the api worker
+    Exit(std::sync::Arc<crate::settings::OriginalState>, Callback<()>),
+    GetRunningGame(Callback<Option<u64>>),
     GetProvider(String, Callback<crate::persist::DriverJson>),
+    Profile(ProfileMessage),
+    History(HistoryMessage),
+    SettingsFileChanged(std::path::PathBuf), // filename in settings dir
 }
 
 pub enum BatteryMessage {
@@ -80,6 +103,7 @@ pub enum CpuMessage {
     SetCpuGovernor(usize, String),
     SetCpusGovernor(Vec<String>),
     GetCpusGovernor(Callback<Vec<String>>),
+    GetActual(Callback<super::CpusActual>),
 }
 
 impl CpuMessage {
@@ -174,6 +198,7 @@ impl CpuMessage {
                 }
                 cb(result);
             }
+            Self::GetActual(cb) => cb(settings.actual()),
         }
         dirty
     }
@@ -199,6 +224,7 @@ pub enum GpuMessage {
     GetClockLimits(Callback<Option<MinMax<u64>>>),
     SetSlowMemory(bool),
     GetSlowMemory(Callback<bool>),
+    GetActual(Callback<super::GpuActual>),
 }
 
 impl GpuMessage {
@@ -211,6 +237,7 @@ impl GpuMessage {
             Self::GetClockLimits(cb) => cb(settings.get_clock_limits().map(|x| x.to_owned())),
             Self::SetSlowMemory(val) => *settings.slow_memory() = val,
             Self::GetSlowMemory(cb) => cb(*settings.slow_memory()),
+            Self::GetActual(cb) => cb(settings.actual()),
         }
         dirty
     }
@@ -249,12 +276,452 @@ impl GeneralMessage {
     }
 }
 
+pub enum ProfileMessage {
+    List(Callback<Vec<super::ProfileInfo>>),
+    Rename(u64, String, Callback<Result<(), String>>), // (id, new name)
+    Duplicate(u64, u64, Callback<Result<(), String>>), // (source id, destination id)
+    Delete(u64, Callback<Result<(), String>>),
+    SetParent(Option<String>, Callback<Result<(), String>>), // (parent filename)
+    GetLayers(Callback<Result<super::ProfileLayers, String>>),
+    Export(Vec<String>, Callback<Result<crate::persist::BundleJson, String>>), // (filenames, or all if empty)
+    Import(crate::persist::BundleJson, bool, Callback<Result<super::ImportResult, String>>), // (bundle, overwrite existing profiles)
+}
+
+impl ProfileMessage {
+    fn process(self, settings: &mut Settings) -> bool {
+        match self {
+            Self::List(cb) => {
+                cb(Self::list(settings));
+                false
+            }
+            Self::Rename(id, name, cb) => {
+                let filename = crate::persist::profile_filename(id);
+                if Self::is_active(settings, &filename) {
+                    log::info!("Renaming active profile {} to `{}`", filename, name);
+                    settings.general.name(name);
+                    cb(Ok(()));
+                    true
+                } else {
+                    cb(Self::rename_file(&filename, name));
+                    false
+                }
+            }
+            Self::Duplicate(src_id, dst_id, cb) => {
+                let src_filename = crate::persist::profile_filename(src_id);
+                let dst_filename = crate::persist::profile_filename(dst_id);
+                let mut json = if Self::is_active(settings, &src_filename) {
+                    settings.json()
+                } else {
+                    match SettingsJson::open(crate::utility::settings_dir().join(&src_filename)) {
+                        Ok(x) => x,
+                        Err(e) => {
+                            cb(Err(format!("Failed to open {}: {}", src_filename, e)));
+                            return false;
+                        }
+                    }
+                };
+                json.persistent = true;
+                let dst_path = crate::utility::settings_dir().join(&dst_filename);
+                if let Err(e) = json.save(&dst_path) {
+                    cb(Err(format!("Failed to save {}: {}", dst_filename, e)));
+                    return false;
+                }
+                log::info!("Duplicated profile {} to {}", src_filename, dst_filename);
+                if Self::is_active(settings, &dst_filename) {
+                    // the active profile was overwritten, so reload it
+                    let name = json.name.clone();
+                    if let Err(e) = settings.load_file(dst_filename.into(), name, false) {
+                        cb(Err(e.msg));
+                        return true;
+                    }
+                    cb(Ok(()));
+                    true
+                } else {
+                    cb(Ok(()));
+                    false
+                }
+            }
+            Self::Delete(id, cb) => {
+                let filename = crate::persist::profile_filename(id);
+                let children = Self::children(settings, &filename);
+                if !children.is_empty() {
+                    cb(Err(format!(
+                        "Cannot delete {} since {} inherit from it",
+                        filename,
+                        children.join(", ")
+                    )));
+                    return false;
+                }
+                if Self::is_active(settings, &filename) {
+                    // non-persistent settings files are deleted on save
+                    log::info!("Deleting active profile {}", filename);
+                    *settings.general.persistent() = false;
+                    cb(Ok(()));
+                    true
+                } else {
+                    let path = crate::utility::settings_dir().join(&filename);
+                    cb(std::fs::remove_file(&path)
+                        .map_err(|e| format!("Failed to delete {}: {}", path.display(), e)));
+                    false
+                }
+            }
+            Self::SetParent(parent, cb) => {
+                if let Some(parent) = parent.as_deref().filter(|p| !crate::persist::is_profile_filename(p)) {
+                    cb(Err(format!("Invalid parent profile filename `{}`", parent)));
+                    return false;
+                }
+                let mut json = settings.json();
+                json.parent = parent.clone();
+                let path = crate::utility::settings_dir().join(settings.general.get_path());
+                // make sure the parent can actually be inherited from before using it
+                if let Err(e) = json.resolve_parent(&path) {
+                    cb(Err(e.to_string()));
+                    return false;
+                }
+                log::info!("Setting parent of profile {} to {:?}", path.display(), parent);
+                settings.general.parent(parent);
+                cb(Ok(()));
+                true
+            }
+            Self::GetLayers(cb) => {
+                let json = settings.json();
+                let path = crate::utility::settings_dir().join(settings.general.get_path());
+                cb(json
+                    .layer_fields(&path)
+                    .map(|(overridden, inherited)| super::ProfileLayers {
+                        parent: json.parent.clone(),
+                        overridden,
+                        inherited,
+                    })
+                    .map_err(|e| e.to_string()));
+                false
+            }
+            Self::Export(filenames, cb) => {
+                cb(Self::export(settings, filenames));
+                false
+            }
+            Self::Import(bundle, overwrite, cb) => {
+                let result = Self::import(settings, bundle, overwrite);
+                let reloaded = result
+                    .as_ref()
+                    .map(|r| r.imported.iter().any(|p| Self::is_active(settings, &p.filename)))
+                    .unwrap_or(false);
+                if reloaded {
+                    // the active profile was overwritten, so reload it
+                    let filename = settings.general.get_path().to_owned();
+                    let name = settings.general.get_name().to_owned();
+                    if let Err(e) = settings.load_file(filename, name, false) {
+                        log::error!("Failed to reload imported profile: {}", e);
+                    }
+                }
+                cb(result);
+                reloaded
+            }
+        }
+    }
+
+    /// Message may write to settings files (including the active one)
+    fn writes_files(&self) -> bool {
+        matches!(
+            self,
+            Self::Rename(..) | Self::Duplicate(..) | Self::Delete(..) | Self::SetParent(..) | Self::Import(..)
+        )
+    }
+
+    fn export(
+        settings: &Settings,
+        mut filenames: Vec<String>,
+    ) -> Result<crate::persist::BundleJson, String> {
+        if filenames.is_empty() {
+            filenames = crate::persist::list_profiles(crate::utility::settings_dir())
+                .map_err(|e| format!("Failed to list profiles: {}", e))?
+                .into_iter()
+                .filter_map(|p| p.path.file_name().map(|f| f.to_string_lossy().into_owned()))
+                .collect();
+        }
+        let mut profiles = Vec::with_capacity(filenames.len());
+        for filename in filenames {
+            let json = if Self::is_active(settings, &filename) {
+                settings.json()
+            } else {
+                SettingsJson::open(crate::utility::settings_dir().join(&filename))
+                    .map_err(|e| format!("Failed to open {}: {}", filename, e))?
+            };
+            profiles.push(crate::persist::BundleProfileJson {
+                settings: serde_json::to_value(&json)
+                    .map_err(|e| format!("Failed to serialize {}: {}", filename, e))?,
+                filename,
+            });
+        }
+        log::info!("Exported {} profiles", profiles.len());
+        Ok(crate::persist::BundleJson {
+            version: crate::persist::BUNDLE_VERSION,
+            provider: settings.cpus.provider(),
+            fingerprint: crate::settings::device_fingerprint(),
+            profiles,
+        })
+    }
+
+    fn import(
+        settings: &mut Settings,
+        bundle: crate::persist::BundleJson,
+        overwrite: bool,
+    ) -> Result<super::ImportResult, String> {
+        if bundle.version > crate::persist::BUNDLE_VERSION {
+            return Err(format!(
+                "Profile bundle version {} is newer than the latest supported version {}",
+                bundle.version,
+                crate::persist::BUNDLE_VERSION
+            ));
+        }
+        let mut warnings = crate::settings::device_fingerprint().differences(&bundle.fingerprint);
+        let fingerprint_match = warnings.is_empty();
+        let local_provider = settings.cpus.provider();
+        if bundle.provider != local_provider {
+            warnings.push(format!(
+                "driver {:?} does not match {:?}",
+                bundle.provider, local_provider
+            ));
+        }
+        for warning in &warnings {
+            log::warn!("Importing profiles from a different device: {}", warning);
+        }
+        // check everything before writing anything, so that a bad bundle isn't imported halfway
+        let mut profiles = Vec::with_capacity(bundle.profiles.len());
+        let mut conflicts = Vec::new();
+        for profile in bundle.profiles {
+            let filename = profile.filename;
+            if !crate::persist::is_profile_filename(&filename) {
+                return Err(format!("Invalid profile filename `{}`", filename));
+            }
+            if crate::utility::settings_dir().join(&filename).exists() {
+                conflicts.push(filename.clone());
+            }
+            let mut json = SettingsJson::from_raw(profile.settings)
+                .map_err(|e| format!("Failed to load {}: {}", filename, e))?;
+            if !fingerprint_match {
+                // a driver chosen for another device may not work here
+                json.provider = None;
+            }
+            if let Some(parent) = &json.parent {
+                if !crate::persist::is_profile_filename(parent)
+                    || !crate::utility::settings_dir().join(parent).exists()
+                {
+                    warnings.push(format!(
+                        "{} inherits from {} which does not exist, so it was imported whole",
+                        filename, parent
+                    ));
+                    json.parent = None;
+                }
+            }
+            json.persistent = true;
+            let (json, clamped) = settings.clamp_json(json);
+            profiles.push((filename, json, clamped));
+        }
+        if !overwrite && !conflicts.is_empty() {
+            return Err(format!(
+                "Profiles {} already exist; import again with overwrite to replace them",
+                conflicts.join(", ")
+            ));
+        }
+        let mut imported = Vec::with_capacity(profiles.len());
+        for (filename, json, clamped) in profiles {
+            json.save(crate::utility::settings_dir().join(&filename))
+                .map_err(|e| format!("Failed to save {}: {}", filename, e))?;
+            log::info!("Imported profile {} (`{}`)", filename, json.name);
+            imported.push(super::ImportedProfile {
+                filename,
+                name: json.name,
+                clamped,
+            });
+        }
+        Ok(super::ImportResult {
+            imported,
+            fingerprint_match,
+            warnings,
+        })
+    }
+
+    /// Filenames of the profiles which inherit directly from `filename`
+    fn children(settings: &Settings, filename: &str) -> Vec<String> {
+        let mut children: Vec<String> = crate::persist::list_profiles(crate::utility::settings_dir())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|p| p.settings.parent.as_deref() == Some(filename))
+            .filter_map(|p| p.path.file_name().map(|f| f.to_string_lossy().into_owned()))
+            .filter(|f| !Self::is_active(settings, f))
+            .collect();
+        // the active profile's parent may not have been saved yet
+        if settings.general.get_parent() == Some(filename) {
+            children.push(settings.general.get_path().display().to_string());
+        }
+        children
+    }
+
+    fn is_active(settings: &Settings, filename: &str) -> bool {
+        settings.general.get_path() == std::path::Path::new(filename)
+    }
+
+    fn list(settings: &Settings) -> Vec<super::ProfileInfo> {
+        let profiles = match crate::persist::list_profiles(crate::utility::settings_dir()) {
+            Ok(x) => x,
+            Err(e) => {
+                log::error!("Failed to list profiles: {}", e);
+                return Vec::new();
+            }
+        };
+        profiles
+            .into_iter()
+            .map(|profile| {
+                let filename = profile
+                    .path
+                    .file_name()
+                    .map(|f| f.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                super::ProfileInfo {
+                    id: profile.id,
+                    active: Self::is_active(settings, &filename),
+                    filename,
+                    name: profile.settings.name,
+                    driver: profile.settings.provider,
+                    persistent: profile.settings.persistent,
+                    parent: profile.settings.parent,
+                    modified: profile
+                        .modified
+                        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                        .map(|d| d.as_secs()),
+                }
+            })
+            .collect()
+    }
+
+    fn rename_file(filename: &str, name: String) -> Result<(), String> {
+        let path = crate::utility::settings_dir().join(filename);
+        let mut json =
+            SettingsJson::open(&path).map_err(|e| format!("Failed to open {}: {}", filename, e))?;
+        log::info!("Renaming profile {} from `{}` to `{}`", filename, json.name, name);
+        json.name = name;
+        json.save(&path)
+            .map_err(|e| format!("Failed to save {}: {}", filename, e))
+    }
+}
+
+pub enum HistoryMessage {
+    Undo(Callback<bool>),
+    Redo(Callback<bool>),
+    Get(Callback<super::SettingsHistory>),
+}
+
+/// State of the api worker which outlives it, so that it can be restarted after a crash
+/// without losing messages, undo history or errors, and which the other workers can see
+#[derive(Clone)]
+pub struct SharedState {
+    intake: Arc<Mutex<Receiver<ApiMessage>>>,
+    /// Whether the active profile enforces its settings, so that enforce_worker only asks for enforcement then
+    pub enforcing: Arc<AtomicBool>,
+    /// Whether the active profile has temperature rules or the thermal governor is on,
+    /// so that power_worker only asks for temperature checks then
+    pub watching_temperatures: Arc<AtomicBool>,
+    /// System defaults are in use after a failed startup, so the main profile must not be overwritten
+    /// (or deleted) until the user loads other settings, makes these persistent or dismisses the warning
+    pub recovery: Arc<AtomicBool>,
+    /// Filename of the active settings, to load again after a crash
+    pub active_path: Arc<Mutex<std::path::PathBuf>>,
+    history: Arc<Mutex<HashMap<std::path::PathBuf, History>>>,
+    /// Errors from the last time each kind of apply ran (e.g. `on_set`), which are cleared when it succeeds
+    errors: Arc<Mutex<std::collections::BTreeMap<&'static str, Vec<super::ApplyError>>>>,
+}
+
+impl SharedState {
+    fn new(intake: Receiver<ApiMessage>) -> Self {
+        Self {
+            intake: Arc::new(Mutex::new(intake)),
+            enforcing: Arc::new(AtomicBool::new(false)),
+            watching_temperatures: Arc::new(AtomicBool::new(false)),
+            recovery: Arc::new(AtomicBool::new(false)),
+            active_path: Arc::new(Mutex::new(crate::consts::DEFAULT_SETTINGS_FILE.into())),
+            history: Arc::new(Mutex::new(HashMap::new())),
+            errors: Arc::new(Mutex::new(std::collections::BTreeMap::new())),
+        }
+    }
+}
+
+/// Lock state shared with a previous api worker, which may have crashed while holding it
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
 pub struct ApiMessageHandler {
-    intake: Receiver<ApiMessage>,
+    shared: SharedState,
     on_empty: Vec<Callback<()>>,
+    /// The settings were just restored from history, so they shouldn't be recorded again
+    restored: bool,
+    /// Filename and contents of the active settings file when it was last loaded or saved,
+    /// to tell external edits apart from our own
+    known_file: Option<(std::path::PathBuf, Option<serde_json::Value>)>,
+    /// App id of the game which is running, as detected by game_worker
+    running_game: Option<u64>,
+    on_apply: Vec<Callback<Vec<super::ApplyError>>>,
+    enforcer: crate::settings::Enforcer,
+    /// Set by an exit request, which is handled after the rest of the batch is saved
+    exiting: Option<(Arc<crate::settings::OriginalState>, Callback<()>)>,
+}
+
+/// Raw contents of a settings file, or `None` if it doesn't exist (or isn't valid JSON)
+fn read_settings_file(filename: &std::path::Path) -> Option<serde_json::Value> {
+    let file = std::fs::File::open(crate::utility::settings_dir().join(filename)).ok()?;
+    serde_json::from_reader(file).ok()
+}
+
+/// Load the profile of a game, or the main profile when no game is running.
+/// A game without a profile gets a new non-persistent one, like when the frontend switches to it,
+/// named after its app id until the frontend loads it with the game's name.
+fn load_game_profile(settings: &mut Settings, app: Option<u64>) -> bool {
+    let filename = app
+        .map(crate::persist::profile_filename)
+        .unwrap_or_else(|| crate::consts::DEFAULT_SETTINGS_FILE.to_owned());
+    if settings.general.get_path() == std::path::Path::new(&filename) {
+        // already loaded (probably by the frontend)
+        return false;
+    }
+    let result = match app {
+        Some(app) => {
+            let name = read_settings_file(std::path::Path::new(&filename))
+                .and_then(|raw| raw.get("name").and_then(|x| x.as_str()).map(|x| x.to_owned()))
+                .unwrap_or_else(|| app.to_string());
+            settings.load_file(filename.into(), name, false)
+        }
+        None => settings.load_file(
+            filename.into(),
+            crate::consts::DEFAULT_SETTINGS_NAME.to_owned(),
+            true,
+        ),
+    };
+    match result {
+        Ok(success) => log::info!("Loaded settings file for running game {:?}? {}", app, success),
+        Err(e) => log::warn!("Load file err: {}", e),
+    }
+    true
+}
+
+/// Whether the active settings inherit from the settings file `filename`
+fn is_ancestor(settings: &Settings, filename: &std::path::Path) -> bool {
+    const MAX_DEPTH: usize = 16;
+    let mut parent = settings.general.get_parent().map(std::path::PathBuf::from);
+    for _ in 0..MAX_DEPTH {
+        match parent {
+            Some(p) if p == filename => return true,
+            Some(p) => {
+                parent = read_settings_file(&p)
+                    .and_then(|raw| raw.get("parent").and_then(|x| x.as_str()).map(Into::into));
+            }
+            None => return false,
+        }
+    }
+    false
 }
 
-fn print_errors(call_name: &str, errors: Vec<crate::settings::SettingError>) {
+fn print_errors(call_name: &str, errors: &[crate::settings::SettingError]) {
     let mut err_list = String::new();
     errors
         .iter()
@@ -264,10 +731,18 @@ fn print_errors(call_name: &str, errors: Vec<crate::settings::SettingError>) {
 
 impl ApiMessageHandler {
     pub fn process_forever(&mut self, settings: &mut Settings) {
+        self.remember_file(settings);
         //let mut dirty_echo = true; // set everything twice, to make sure PowerTools wins on race conditions
-        while let Ok(msg) = self.intake.recv() {
+        let intake = self.shared.intake.clone();
+        // a crashed api_worker may have poisoned the lock, but the receiver is still fine
+        let intake = lock(&intake);
+        self.share_state(settings);
+        while let Ok(msg) = intake.recv() {
+            lock(&self.shared.history)
+                .entry(settings.general.get_path().to_owned())
+                .or_insert_with(|| History::new(settings.json()));
             let mut dirty = self.process(settings, msg);
-            while let Ok(msg) = self.intake.try_recv() {
+            while let Ok(msg) = intake.try_recv() {
                 dirty |= self.process(settings, msg);
             }
             if dirty
@@ -276,8 +751,14 @@ impl ApiMessageHandler {
                 //dirty_echo = dirty; // echo only once
 
                 // run on_set
-                if let Err(e) = settings.on_set() {
-                    print_errors("on_set", e);
+                let result = settings.on_set();
+                let errors = self.record_errors("on_set", "on_set", result);
+                for func in self.on_apply.drain(..) {
+                    func(errors.clone());
+                }
+                // remember for undo
+                if !std::mem::take(&mut self.restored) {
+                    self.record_history(settings);
                 }
                 // do callbacks
                 for func in self.on_empty.drain(..) {
@@ -288,26 +769,51 @@ impl ApiMessageHandler {
                 let is_persistent = *settings.general.persistent();
                 let save_path =
                     crate::utility::settings_dir().join(settings.general.get_path().clone());
-                if is_persistent {
+                let conflict = self.is_externally_modified(settings);
+                if !self.may_save(settings) {
+                    log::info!(
+                        "Not saving (or deleting) {} while recovering from a failed startup",
+                        save_path.display()
+                    );
+                } else if conflict {
+                    // the pending reload will pick up the file on disk, so only keep a copy of ours
+                    let conflict_path = save_path.with_file_name(format!(
+                        "{}.conflict",
+                        settings.general.get_path().display()
+                    ));
+                    let mut save_json = settings.json();
+                    save_json.persistent = true;
+                    if let Err(e) = save_json.save(&conflict_path) {
+                        log::error!(
+                            "Failed to save conflicting settings to {}: {}",
+                            conflict_path.display(),
+                            e
+                        );
+                    }
+                    log::warn!(
+                        "Settings file {} was changed externally, so it was not overwritten; PowerTools' settings were saved to {} instead",
+                        save_path.display(),
+                        conflict_path.display()
+                    );
+                } else if is_persistent {
                     let settings_clone = settings.json();
                     let save_json: SettingsJson = settings_clone.into();
-                    unwrap_maybe_fatal(save_json.save(&save_path), "Failed to save settings");
-                    if let Some(event) = &settings.general.on_event().on_save {
-                        if !event.is_empty() {
-                            unwrap_maybe_fatal(
-                                std::process::Command::new("/bin/bash")
-                                    .args(&["-c", event])
-                                    .spawn(),
-                                "Failed to start on_save event command",
-                            );
-                        }
+                    if let Err(e) = save_json.save(&save_path) {
+                        log::error!("Failed to save settings to {}: {}", save_path.display(), e);
+                    } else {
+                        settings.run_event("on_save", &[]);
+                        log::debug!("Saved settings to {}", save_path.display());
                     }
-                    log::debug!("Saved settings to {}", save_path.display());
                     if let Err(e) = crate::utility::chown_settings_dir() {
                         log::error!("Failed to change config dir permissions: {}", e);
                     }
                 } else {
-                    if save_path.exists() {
+                    if SettingsJson::is_from_newer_version(&save_path) {
+                        log::warn!(
+                            "Not deleting settings file {} written by a newer version",
+                            save_path.display()
+                        );
+                    } else if save_path.exists() {
                         if let Err(e) = std::fs::remove_file(&save_path) {
                             log::warn!(
                                 "Failed to delete persistent settings file {}: {}",
@@ -321,12 +827,174 @@ impl ApiMessageHandler {
                         log::debug!("Ignored save request for non-persistent settings");
                     }
                 }
+                if !conflict {
+                    // whatever is on disk now was written (or deleted) by us
+                    self.remember_file(settings);
+                }
             } else {
                 log::debug!("Skipping callbacks for non-modify handled message(s)");
+                if !self.on_apply.is_empty() {
+                    // nothing to apply, so the last on_set is still what's in effect
+                    let errors = lock(&self.shared.errors).get("on_set").cloned().unwrap_or_default();
+                    for func in self.on_apply.drain(..) {
+                        func(errors.clone());
+                    }
+                }
+            }
+            self.share_state(settings);
+            if let Some((original, cb)) = self.exiting.take() {
+                self.exit(settings, &original);
+                cb(());
+                return;
             }
         }
     }
 
+    /// Let the other workers (and a restarted api worker) know what the active settings need
+    fn share_state(&self, settings: &Settings) {
+        self.shared.enforcing.store(settings.enforce, Ordering::SeqCst);
+        self.shared
+            .watching_temperatures
+            .store(settings.watches_temperatures(), Ordering::SeqCst);
+        let path = settings.general.get_path();
+        let mut active_path = lock(&self.shared.active_path);
+        if active_path.as_path() != path {
+            *active_path = path.to_owned();
+        }
+    }
+
+    /// Put the hardware back the way it was before PowerTools started, now that everything is saved
+    fn exit(&mut self, settings: &mut Settings, original: &crate::settings::OriginalState) {
+        match settings.restore_system(original) {
+            Ok(()) => log::info!("Restored original state"),
+            Err(e) => print_errors("restore_system()", &e),
+        }
+        // exiting isn't a sign of the startup settings being a problem
+        if let Err(e) = crate::utility::remove_applying_sentinel() {
+            log::error!("Failed to remove applying sentinel: {}", e);
+        }
+        log::info!("api_worker exiting");
+    }
+
+    /// Log and keep the errors from applying settings, replacing the previous ones from `source`
+    fn record_errors(
+        &mut self,
+        source: &'static str,
+        call_name: &str,
+        result: Result<(), Vec<crate::settings::SettingError>>,
+    ) -> Vec<super::ApplyError> {
+        let errors = match result {
+            Ok(()) => Vec::new(),
+            Err(e) => {
+                print_errors(call_name, &e);
+                let time = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                e.into_iter()
+                    .map(|e| super::ApplyError {
+                        source: source.to_owned(),
+                        setting: e.setting,
+                        kind: e.kind,
+                        target: e.target,
+                        msg: e.msg,
+                        time,
+                    })
+                    .collect()
+            }
+        };
+        if errors.is_empty() {
+            lock(&self.shared.errors).remove(source);
+        } else {
+            lock(&self.shared.errors).insert(source, errors.clone());
+        }
+        errors
+    }
+
+    fn remember_file(&mut self, settings: &Settings) {
+        let filename = settings.general.get_path().to_owned();
+        let contents = read_settings_file(&filename);
+        self.known_file = Some((filename, contents));
+    }
+
+    /// Whether the active settings file changed since it was last loaded or saved
+    fn is_externally_modified(&self, settings: &Settings) -> bool {
+        match &self.known_file {
+            Some((filename, contents)) if filename == settings.general.get_path() => {
+                read_settings_file(filename) != *contents
+            }
+            _ => false,
+        }
+    }
+
+    /// Reload the active settings if `filename` is the active settings file (or one it inherits from)
+    /// and it was changed by something other than PowerTools
+    fn reload_if_changed(&mut self, settings: &mut Settings, filename: std::path::PathBuf) -> bool {
+        let active = settings.general.get_path().to_owned();
+        if filename == active {
+            if !self.is_externally_modified(settings) {
+                log::debug!("Ignoring unchanged settings file {}", filename.display());
+                return false;
+            }
+        } else if !is_ancestor(settings, &filename) {
+            return false;
+        }
+        log::info!(
+            "Settings file {} was changed externally, reloading {}",
+            filename.display(),
+            active.display()
+        );
+        let name = settings.general.get_name().to_owned();
+        if let Err(e) = settings.load_file(active, name, false) {
+            log::warn!("Reload file err: {}", e);
+        }
+        self.remember_file(settings);
+        true
+    }
+
+    fn record_history(&mut self, settings: &Settings) {
+        let snapshot = settings.json();
+        let mut histories = lock(&self.shared.history);
+        match histories.get_mut(settings.general.get_path()) {
+            Some(history) => {
+                if history.record(snapshot) {
+                    log::debug!("Recorded settings history for {}", settings.general.get_path().display());
+                }
+            }
+            None => {
+                histories.insert(settings.general.get_path().to_owned(), History::new(snapshot));
+            }
+        }
+    }
+
+    fn process_history(&mut self, settings: &mut Settings, message: HistoryMessage) -> bool {
+        let mut histories = lock(&self.shared.history);
+        let history = histories.get_mut(settings.general.get_path());
+        let (snapshot, cb) = match (message, history) {
+            (HistoryMessage::Undo(cb), Some(history)) => (history.undo(), cb),
+            (HistoryMessage::Redo(cb), Some(history)) => (history.redo(), cb),
+            (HistoryMessage::Get(cb), history) => {
+                cb(history
+                    .map(|h| h.info())
+                    .unwrap_or_else(|| History::new(settings.json()).info()));
+                return false;
+            }
+            (HistoryMessage::Undo(cb), None) | (HistoryMessage::Redo(cb), None) => (None, cb),
+        };
+        if let Some(snapshot) = snapshot {
+            let path = settings.general.get_path().to_owned();
+            log::info!("Restoring settings for {} from history", path.display());
+            // the drivers are kept as they are, so that their hardware state (e.g. whether the charger was plugged in) stays
+            settings.load_json(snapshot);
+            self.restored = true;
+            cb(true);
+            true
+        } else {
+            cb(false);
+            false
+        }
+    }
+
     pub fn process(&mut self, settings: &mut Settings, message: ApiMessage) -> bool {
         match message {
             ApiMessage::Battery(x) => x.process(settings.battery.as_mut()),
@@ -334,47 +1002,66 @@ impl ApiMessageHandler {
             ApiMessage::Gpu(x) => x.process(settings.gpu.as_mut()),
             ApiMessage::General(x) => x.process(settings.general.as_mut()),
             ApiMessage::OnResume => {
-                if let Err(e) = settings.on_resume() {
-                    print_errors("on_resume", e);
-                }
+                let result = match (settings.on_resume(), settings.reapply_rules()) {
+                    (Ok(()), Ok(())) => Ok(()),
+                    (a, b) => Err(a.err().into_iter().chain(b.err()).flatten().collect()),
+                };
+                self.record_errors("on_resume", "on_resume", result);
                 false
             }
             ApiMessage::OnPluggedIn => {
-                if let Err(e) = settings.on_power_event(PowerMode::PluggedIn) {
-                    print_errors("on_power_event(PluggedIn)", e);
-                }
-                true
+                let result = settings.on_power_event(PowerMode::PluggedIn);
+                self.record_errors("on_power_event", "on_power_event(PluggedIn)", result);
+                false // on_power_event() should apply everything
             }
             ApiMessage::OnUnplugged => {
-                if let Err(e) = settings.on_power_event(PowerMode::PluggedOut) {
-                    print_errors("on_power_event(PluggedOut)", e);
-                }
-                true
+                let result = settings.on_power_event(PowerMode::PluggedOut);
+                self.record_errors("on_power_event", "on_power_event(PluggedOut)", result);
+                false // on_power_event() should apply everything
             }
             ApiMessage::OnChargeChange(charge) => {
-                if let Err(e) = settings.on_power_event(PowerMode::BatteryCharge(charge)) {
-                    print_errors(&format!("on_power_event(BatteryCharge={:#0.5})", charge), e);
-                }
-                true
+                let result = settings.on_power_event(PowerMode::BatteryCharge(charge));
+                self.record_errors(
+                    "on_power_event",
+                    &format!("on_power_event(BatteryCharge={:#0.5})", charge),
+                    result,
+                );
+                false // on_power_event() should apply everything
             }
-            ApiMessage::PowerVibeCheck => {
+            ApiMessage::PowerVibeCheck(charge_only) => {
                 match settings.battery.check_power() {
-                    Err(e) => print_errors("check_power()", e),
+                    Err(e) => print_errors("check_power()", &e),
                     Ok(events) => {
+                        // plug events already came from uevents
+                        let events = events.into_iter().filter(|ev| {
+                            !charge_only || matches!(ev, PowerMode::BatteryCharge(_))
+                        });
                         for ev in events {
                             let name = format!("on_power_event([vibe]{:?})", ev);
-                            if let Err(e) = settings.on_power_event(ev) {
-                                print_errors(&name, e);
-                            }
+                            let result = settings.on_power_event(ev);
+                            self.record_errors("on_power_event", &name, result);
                         }
                     }
                 }
                 false // on_power_event() should apply everything
             }
+            ApiMessage::CheckTemperatures => {
+                let result = settings.check_temperatures();
+                self.record_errors("check_temperatures", "check_temperatures()", result);
+                false
+            }
             ApiMessage::WaitForEmptyQueue(callback) => {
                 self.on_empty.push(callback);
                 false
             }
+            ApiMessage::WaitForApply(callback) => {
+                self.on_apply.push(callback);
+                false
+            }
+            ApiMessage::GetErrors(cb) => {
+                cb(lock(&self.shared.errors).values().flatten().cloned().collect());
+                false
+            }
             ApiMessage::LoadSettings(id, name) => {
                 let path = format!("{}.json", id);
                 match settings.load_file(path.into(), name, false) {
@@ -407,6 +1094,41 @@ impl ApiMessageHandler {
                 });
                 false
             }
+            ApiMessage::GetLimitViolations(cb) => {
+                cb(settings.limit_violations.clone());
+                false
+            }
+            ApiMessage::SetRules(rules) => {
+                settings.set_rules(rules);
+                true
+            }
+            ApiMessage::GetRules(cb) => {
+                cb(settings.rules.clone());
+                false
+            }
+            ApiMessage::SetEnforce(enforce) => {
+                settings.enforce = enforce;
+                true
+            }
+            ApiMessage::GetEnforce(cb) => {
+                cb(settings.enforce);
+                false
+            }
+            ApiMessage::Enforce => {
+                if settings.enforce {
+                    // power and temperature rules are what should be in effect while they're active
+                    let enforcer = &mut self.enforcer;
+                    let result = settings.with_active_rules(|settings| enforcer.enforce(settings));
+                    self.record_errors("enforce", "enforce()", result);
+                }
+                false
+            }
+            ApiMessage::ApplyChanges(changes) => {
+                // not dirty, since the changes are kept on top of the settings instead of in them
+                let result = settings.schedule_changes(changes);
+                self.record_errors("on_set", "on_set", result);
+                false
+            }
             ApiMessage::GetProvider(name, cb) => {
                 cb(match &name as &str {
                     "battery" => settings.battery.provider(),
@@ -416,17 +1138,134 @@ impl ApiMessageHandler {
                 });
                 false
             }
+            ApiMessage::Profile(x) => {
+                let writes = x.writes_files();
+                let dirty = x.process(settings);
+                if writes {
+                    // the active settings file may have been written and reloaded, which isn't an external edit
+                    self.remember_file(settings);
+                }
+                dirty
+            }
+            ApiMessage::History(x) => self.process_history(settings, x),
+            ApiMessage::SettingsFileChanged(filename) => self.reload_if_changed(settings, filename),
+            ApiMessage::GetThermalStatus(cb) => {
+                cb(settings.thermal_status());
+                false
+            }
+            ApiMessage::SetThermalConfig(config) => {
+                let path = crate::utility::settings_dir().join(crate::consts::THERMAL_GOVERNOR_FILE);
+                if let Err(e) = config.save(&path) {
+                    log::error!("Failed to save thermal governor config to {}: {}", path.display(), e);
+                }
+                settings.set_thermal_config(config);
+                false
+            }
+            ApiMessage::Exit(original, cb) => {
+                self.exiting = Some((original, cb));
+                false
+            }
+            ApiMessage::RunningGameChanged(app) => {
+                self.running_game = app;
+                load_game_profile(settings, app)
+            }
+            ApiMessage::GetRunningGame(cb) => {
+                cb(self.running_game);
+                false
+            }
         }
     }
 
+    /// Start out recovering from a failed startup, with system defaults in place of the main profile
+    pub fn set_recovery(&mut self, recovery: bool) {
+        self.shared.recovery.store(recovery, Ordering::SeqCst);
+    }
+
+    /// Whether the active settings file may be saved (or deleted), which ends recovery
+    /// once the user has loaded other settings or made the defaults persistent
+    fn may_save(&mut self, settings: &Settings) -> bool {
+        if settings.general.get_persistent()
+            || settings.general.get_path() != std::path::Path::new(crate::consts::DEFAULT_SETTINGS_FILE)
+        {
+            crate::utility::end_recovery(&self.shared.recovery);
+        }
+        !self.shared.recovery.load(Ordering::SeqCst)
+    }
+
+    /// State to hand to a new handler when api_worker is restarted, and to the other workers
+    pub fn shared(&self) -> SharedState {
+        self.shared.clone()
+    }
+
     pub fn new() -> (Self, Sender<ApiMessage>) {
         let (tx, rx) = mpsc::channel();
-        (
-            Self {
-                intake: rx,
-                on_empty: Vec::with_capacity(4),
-            },
-            tx,
-        )
+        (Self::with_shared(SharedState::new(rx)), tx)
+    }
+
+    pub fn with_shared(shared: SharedState) -> Self {
+        Self {
+            shared,
+            on_empty: Vec::with_capacity(4),
+            restored: false,
+            known_file: None,
+            running_game: None,
+            on_apply: Vec::new(),
+            enforcer: Default::default(),
+            exiting: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovery_keeps_main_profile_test() {
+        let (mut handler, _sender) = ApiMessageHandler::new();
+        let mut settings = Settings::system_default(
+            crate::consts::DEFAULT_SETTINGS_FILE.into(),
+            crate::consts::DEFAULT_SETTINGS_NAME.into(),
+        );
+        assert!(handler.may_save(&settings));
+        // booted with system defaults after a stale applying sentinel
+        handler.set_recovery(true);
+        assert!(!settings.general.get_persistent());
+        assert!(!handler.may_save(&settings));
+        assert!(!handler.may_save(&settings));
+        // the user made the defaults their main profile
+        *settings.general.persistent() = true;
+        assert!(handler.may_save(&settings));
+        *settings.general.persistent() = false;
+        assert!(handler.may_save(&settings));
+    }
+
+    #[test]
+    fn respawn_keeps_history_test() {
+        let (mut handler, _sender) = ApiMessageHandler::new();
+        let mut settings = Settings::system_default("test.json".into(), "Test".into());
+        handler.record_history(&settings);
+        settings.enforce = true;
+        handler.record_history(&settings);
+        handler.share_state(&settings);
+        let result = Err(vec![crate::settings::SettingError {
+            msg: "test".to_owned(),
+            setting: crate::settings::SettingVariant::General,
+            kind: crate::settings::SettingErrorKind::Io,
+            target: None,
+        }]);
+        handler.record_errors("on_set", "on_set", result);
+        // api_worker crashed and was restarted
+        let mut handler = ApiMessageHandler::with_shared(handler.shared());
+        assert_eq!(
+            *handler.shared.active_path.lock().unwrap(),
+            std::path::PathBuf::from("test.json")
+        );
+        assert_eq!(lock(&handler.shared.errors).get("on_set").map(|e| e.len()), Some(1));
+        let (tx, rx) = mpsc::channel();
+        let undo = HistoryMessage::Undo(Box::new(move |x| tx.send(x).unwrap()));
+        assert!(handler.process_history(&mut settings, undo));
+        assert!(rx.recv().unwrap());
+        assert!(!settings.enforce);
     }
 }
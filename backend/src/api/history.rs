@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+use std::time::SystemTime;
+
+use crate::persist::SettingsJson;
+
+/// Bounded history of settings snapshots for a single profile, for undo and redo
+pub(super) struct History {
+    snapshots: VecDeque<(SystemTime, SettingsJson)>,
+    /// Index of the snapshot matching the current settings
+    position: usize,
+}
+
+impl History {
+    pub fn new(current: SettingsJson) -> Self {
+        let mut snapshots = VecDeque::with_capacity(crate::consts::SETTINGS_HISTORY_SIZE);
+        snapshots.push_back((SystemTime::now(), current));
+        Self {
+            snapshots,
+            position: 0,
+        }
+    }
+
+    /// Add a snapshot after the current one, discarding anything which could have been redone.
+    /// Nothing is recorded if the snapshot is the same as the current one.
+    pub fn record(&mut self, snapshot: SettingsJson) -> bool {
+        if let Some((_, current)) = self.snapshots.get(self.position) {
+            if current.changed_fields(&snapshot).is_empty() {
+                return false;
+            }
+        }
+        self.snapshots.truncate(self.position + 1);
+        self.snapshots.push_back((SystemTime::now(), snapshot));
+        while self.snapshots.len() > crate::consts::SETTINGS_HISTORY_SIZE {
+            self.snapshots.pop_front();
+        }
+        self.position = self.snapshots.len() - 1;
+        true
+    }
+
+    pub fn undo(&mut self) -> Option<SettingsJson> {
+        if self.position == 0 {
+            None
+        } else {
+            self.position -= 1;
+            self.snapshots.get(self.position).map(|(_, x)| x.clone())
+        }
+    }
+
+    pub fn redo(&mut self) -> Option<SettingsJson> {
+        if self.position + 1 >= self.snapshots.len() {
+            None
+        } else {
+            self.position += 1;
+            self.snapshots.get(self.position).map(|(_, x)| x.clone())
+        }
+    }
+
+    pub fn info(&self) -> super::SettingsHistory {
+        let mut previous: Option<&SettingsJson> = None;
+        let entries = self
+            .snapshots
+            .iter()
+            .enumerate()
+            .map(|(i, (time, snapshot))| {
+                let entry = super::HistoryEntry {
+                    time: time
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0),
+                    changed: previous
+                        .map(|p| p.changed_fields(snapshot))
+                        .unwrap_or_default(),
+                    current: i == self.position,
+                };
+                previous = Some(snapshot);
+                entry
+            })
+            .collect();
+        super::SettingsHistory {
+            position: self.position,
+            can_undo: self.position != 0,
+            can_redo: self.position + 1 < self.snapshots.len(),
+            entries,
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::{atomic::{AtomicU64, Ordering}, Arc};
+use std::sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Arc, Mutex};
 
 use serde::{Deserialize, Serialize};
 
@@ -39,6 +39,10 @@ fn get_dev_messages() -> Vec<ApiMessage> {
 
 pub struct MessageHandler {
     seen: Arc<AtomicU64>,
+    /// Messages from the back-end itself (without an id), shown until dismissed
+    local: Arc<Mutex<Vec<ApiMessage>>>,
+    /// Set while recovering from a failed startup, which dismissing the local messages ends
+    recovery: Option<Arc<AtomicBool>>,
 }
 
 impl MessageHandler {
@@ -63,16 +67,29 @@ impl MessageHandler {
         };
         Self {
             seen: Arc::new(AtomicU64::new(last_seen_id)),
+            local: Arc::new(Mutex::new(Vec::new())),
+            recovery: None,
         }
     }
 
+    pub fn set_recovery(&mut self, recovery: Arc<AtomicBool>) {
+        self.recovery = Some(recovery);
+    }
+
+    pub fn push(&self, message: ApiMessage) {
+        self.local.lock().unwrap().push(message);
+    }
+
     pub fn to_callables(self) -> (AsyncMessageGetter, AsyncMessageDismisser) {
         (
             AsyncMessageGetter {
                 seen: self.seen.clone(),
+                local: self.local.clone(),
             },
             AsyncMessageDismisser {
                 seen: self.seen.clone(),
+                local: self.local.clone(),
+                recovery: self.recovery.clone(),
             }
         )
     }
@@ -80,6 +97,7 @@ impl MessageHandler {
 
 pub struct AsyncMessageGetter {
     seen: Arc<AtomicU64>,
+    local: Arc<Mutex<Vec<ApiMessage>>>,
 }
 
 impl AsyncMessageGetter {
@@ -103,14 +121,18 @@ impl AsyncCallable for AsyncMessageGetter {
             self.seen.load(Ordering::Relaxed)
         };
         let mut messages = get_dev_messages();
+        let local = self.local.lock().unwrap();
         Self::remove_before_id(since, messages.drain(..))
             .filter_map(|msg| serde_json::to_string(&msg).ok().map(|x| Primitive::Json(x)))
+            .chain(local.iter().filter_map(|msg| serde_json::to_string(msg).ok().map(Primitive::Json)))
             .collect()
     }
 }
 
 pub struct AsyncMessageDismisser {
     seen: Arc<AtomicU64>,
+    local: Arc<Mutex<Vec<ApiMessage>>>,
+    recovery: Option<Arc<AtomicBool>>,
 }
 
 #[async_trait::async_trait]
@@ -134,7 +156,10 @@ impl AsyncCallable for AsyncMessageDismisser {
                 log::error!("Failed to write seen id to {}: {}", filename.display(), e);
             }
         } else {
-            // TODO clear non-dev messages in cache
+            self.local.lock().unwrap().clear();
+            if let Some(recovery) = &self.recovery {
+                crate::utility::end_recovery(recovery);
+            }
         }
         vec![true.into()]
     }
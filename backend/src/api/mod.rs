@@ -5,6 +5,7 @@ pub mod cpu;
 pub mod general;
 pub mod gpu;
 pub mod handler;
+mod history;
 pub mod message;
 mod utility;
 
@@ -1,8 +1,10 @@
+use std::path::Path;
+use std::sync::atomic::Ordering;
 use std::thread::{self, JoinHandle};
 
-use crate::settings::Settings;
-//use crate::utility::{unwrap_lock, unwrap_maybe_fatal};
-use crate::api::handler::ApiMessageHandler;
+use crate::consts::{DEFAULT_SETTINGS_FILE, DEFAULT_SETTINGS_NAME};
+use crate::settings::{OnSet, Settings};
+use crate::api::handler::{ApiMessageHandler, SharedState};
 
 pub fn spawn(mut settings: Settings, mut handler: ApiMessageHandler) -> JoinHandle<()> {
     thread::spawn(move || {
@@ -11,3 +13,35 @@ pub fn spawn(mut settings: Settings, mut handler: ApiMessageHandler) -> JoinHand
         log::warn!("api_worker completed!");
     })
 }
+
+/// The settings file `filename` from disk, falling back to the main profile and then system defaults,
+/// or system defaults after a failed startup (see `ApiMessageHandler::set_recovery()`)
+pub fn load_settings(filename: &Path, recovery: bool) -> Settings {
+    if recovery {
+        return Settings::system_default(DEFAULT_SETTINGS_FILE.into(), DEFAULT_SETTINGS_NAME.into());
+    }
+    let open = |filename: &Path| {
+        crate::persist::SettingsJson::open(crate::utility::settings_dir().join(filename))
+            .map(|settings| Settings::from_json(settings, filename.to_owned()))
+    };
+    open(filename)
+        .or_else(|e| {
+            if filename != Path::new(DEFAULT_SETTINGS_FILE) {
+                log::warn!("Failed to load {}, using the main profile instead: {}", filename.display(), e);
+            }
+            open(Path::new(DEFAULT_SETTINGS_FILE))
+        })
+        .unwrap_or_else(|_| Settings::system_default(DEFAULT_SETTINGS_FILE.into(), DEFAULT_SETTINGS_NAME.into()))
+}
+
+/// Start again after a crash, with the active settings reloaded from disk and applied.
+/// Messages sent in the meantime are still handled, and undo history and errors are kept, since they're shared.
+pub fn respawn(shared: SharedState) -> JoinHandle<()> {
+    let active_path = shared.active_path.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let mut settings = load_settings(&active_path, shared.recovery.load(Ordering::SeqCst));
+    if let Err(e) = settings.on_set() {
+        e.iter()
+            .for_each(|e| log::error!("Restart Settings.on_set() error: {}", e));
+    }
+    spawn(settings, ApiMessageHandler::with_shared(shared))
+}
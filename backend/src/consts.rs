@@ -5,7 +5,25 @@ pub const PACKAGE_VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
 pub const DEFAULT_SETTINGS_FILE: &str = "default_settings.json";
 pub const DEFAULT_SETTINGS_NAME: &str = "Main";
+/// Number of previous versions to keep of each settings file
+pub const SETTINGS_BACKUP_COUNT: usize = 3;
+/// Minimum age of the newest backup of a settings file before another one is made
+pub const SETTINGS_BACKUP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+/// Number of changes to remember for undo and redo, per profile
+pub const SETTINGS_HISTORY_SIZE: usize = 32;
 
 pub const LIMITS_FILE: &str = "limits_cache.json";
+/// Scheduled profile switches
+pub const SCHEDULE_FILE: &str = "schedule.json";
+/// Schedule entry which was applied last
+pub const SCHEDULE_STATE_FILE: &str = "schedule_state.json";
+/// Thermal safety governor configuration
+pub const THERMAL_GOVERNOR_FILE: &str = "thermal_governor.json";
+
+/// Exists while settings are being applied at startup, until the system has stayed up for `STARTUP_HEALTHY_PERIOD`
+pub const APPLYING_SENTINEL_FILE: &str = "applying_settings";
+pub const STARTUP_HEALTHY_PERIOD: std::time::Duration = std::time::Duration::from_secs(60);
+/// Exists after a failed startup, until the user loads or applies settings or dismisses the warning
+pub const RECOVERY_FILE: &str = "recovering_settings";
 
 pub const MESSAGE_SEEN_ID_FILE: &str = "seen_message.bin";
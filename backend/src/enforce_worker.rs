@@ -0,0 +1,27 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::api::handler::ApiMessage;
+
+const PERIOD: Duration = Duration::from_secs(5);
+
+/// Ask for drifted settings to be re-applied every `PERIOD`, but only while `enforcing`
+/// (i.e. the active profile has enforcement on), so that api_worker isn't woken up for nothing
+pub fn spawn(sender: Sender<ApiMessage>, enforcing: Arc<AtomicBool>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        log::info!("enforce_worker starting...");
+        loop {
+            thread::sleep(PERIOD);
+            if !enforcing.load(Ordering::SeqCst) {
+                continue;
+            }
+            if sender.send(ApiMessage::Enforce).is_err() {
+                log::info!("enforce_worker stopping, api_worker is gone");
+                return;
+            }
+        }
+    })
+}
@@ -0,0 +1,127 @@
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::api::handler::ApiMessage;
+
+const PERIOD: Duration = Duration::from_secs(3);
+
+pub fn spawn(sender: Sender<ApiMessage>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        log::info!("game_worker starting...");
+        let mut last_app: Option<u64> = None;
+        loop {
+            let app = find_running_app();
+            if app != last_app {
+                log::info!("Running game changed from {:?} to {:?}", last_app, app);
+                if sender.send(ApiMessage::RunningGameChanged(app)).is_err() {
+                    log::info!("game_worker stopping, api_worker is gone");
+                    return;
+                }
+                last_app = app;
+            }
+            thread::sleep(PERIOD);
+        }
+        //log::warn!("game_worker completed!");
+    })
+}
+
+/// Find the app id of the running Steam game.
+/// Steam launches games through its `reaper` process with an `AppId=` argument;
+/// when there is no reaper (e.g. older clients), the `SteamAppId` environment variable
+/// of the processes started by the Steam client is used.
+fn find_running_app() -> Option<u64> {
+    let procs = match std::fs::read_dir("/proc") {
+        Ok(x) => x,
+        Err(e) => {
+            log::error!("game_worker failed to read /proc: {}", e);
+            return None;
+        }
+    };
+    let pids: Vec<std::path::PathBuf> = procs
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().bytes().all(|b| b.is_ascii_digit()))
+        .map(|e| e.path())
+        .collect();
+    // processes may exit while scanning, so read errors are expected
+    let reaper_app = pids.iter().find_map(|dir| {
+        std::fs::read(dir.join("cmdline"))
+            .ok()
+            .and_then(|cmdline| app_id_from_cmdline(&cmdline))
+    });
+    if reaper_app.is_some() {
+        return reaper_app;
+    }
+    // (pid, parent pid, name) of every process
+    let stats: Vec<(u32, u32, String)> = pids
+        .iter()
+        .filter_map(|dir| {
+            let pid = dir.file_name()?.to_str()?.parse().ok()?;
+            let stat = std::fs::read_to_string(dir.join("stat")).ok()?;
+            let (ppid, comm) = parse_stat(&stat)?;
+            Some((pid, ppid, comm.to_owned()))
+        })
+        .collect();
+    let steam_pids: Vec<u32> = stats
+        .iter()
+        .filter(|(_, _, comm)| comm == "steam")
+        .map(|(pid, _, _)| *pid)
+        .collect();
+    stats
+        .iter()
+        .filter(|(_, ppid, _)| steam_pids.contains(ppid))
+        .find_map(|(pid, _, _)| {
+            std::fs::read(format!("/proc/{}/environ", pid))
+                .ok()
+                .and_then(|environ| app_id_from_environ(&environ))
+        })
+}
+
+/// Parent pid and name of a process, from its `/proc/<pid>/stat`
+fn parse_stat(stat: &str) -> Option<(u32, &str)> {
+    // the name is in parentheses, and may contain spaces and parentheses itself
+    let comm_start = stat.find('(')? + 1;
+    let comm_end = stat.rfind(')')?;
+    let comm = stat.get(comm_start..comm_end)?;
+    let mut fields = stat.get(comm_end + 1..)?.split_whitespace();
+    let _state = fields.next()?;
+    let ppid = fields.next()?.parse().ok()?;
+    Some((ppid, comm))
+}
+
+/// App id of a Steam reaper process, from its NUL-separated command line
+fn app_id_from_cmdline(cmdline: &[u8]) -> Option<u64> {
+    let mut args = cmdline.split(|b| *b == 0);
+    let exe = args.next()?;
+    if !(exe == b"reaper" || exe.ends_with(b"/reaper")) {
+        return None;
+    }
+    args.filter_map(|arg| arg.strip_prefix(b"AppId="))
+        .filter_map(|id| std::str::from_utf8(id).ok()?.parse::<u64>().ok())
+        .find(|id| *id != 0)
+}
+
+/// `SteamAppId` from a process's NUL-separated environment (0 means not a game)
+fn app_id_from_environ(environ: &[u8]) -> Option<u64> {
+    environ
+        .split(|b| *b == 0)
+        .filter_map(|var| var.strip_prefix(b"SteamAppId="))
+        .filter_map(|id| std::str::from_utf8(id).ok()?.parse::<u64>().ok())
+        .find(|id| *id != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn app_id_parse_test() {
+        let reaper = b"/home/deck/.local/share/Steam/ubuntu12_32/reaper\0SteamLaunch\0AppId=1145360\0--\0game.exe\0";
+        assert_eq!(app_id_from_cmdline(reaper), Some(1145360));
+        assert_eq!(app_id_from_cmdline(b"/usr/bin/bash\0AppId=1145360\0"), None);
+        assert_eq!(app_id_from_environ(b"HOME=/home/deck\0SteamAppId=620\0"), Some(620));
+        assert_eq!(app_id_from_environ(b"SteamAppId=0\0"), None);
+        assert_eq!(parse_stat("4321 (Game (x64)) S 1234 4321 1234 0"), Some((1234, "Game (x64)")));
+        assert_eq!(parse_stat("4321 (steam"), None);
+    }
+}
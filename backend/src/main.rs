@@ -6,12 +6,21 @@ mod state;
 mod consts;
 use consts::*;
 mod power_worker;
+mod enforce_worker;
 mod resume_worker;
+mod game_worker;
+mod reload_worker;
+mod schedule_worker;
+mod signal_worker;
+mod uevent_worker;
+mod watchdog_worker;
 //mod save_worker;
 mod api_worker;
+mod supervisor;
 mod utility;
 
 use settings::OnSet;
+use supervisor::Restart;
 
 use simplelog::{LevelFilter, WriteLogger};
 
@@ -72,17 +81,26 @@ fn main() -> Result<(), ()> {
         log::info!("Updated version file succesfully");
     }
 
-    let _limits_handle = crate::settings::limits_worker_spawn();
+    let mut supervisor = supervisor::Supervisor::new();
+    supervisor.add(
+        "limits_worker",
+        Restart::OnCrash,
+        crate::settings::limits_worker_spawn,
+    );
 
-    let mut loaded_settings =
-        persist::SettingsJson::open(utility::settings_dir().join(DEFAULT_SETTINGS_FILE))
-            .map(|settings| settings::Settings::from_json(settings, DEFAULT_SETTINGS_FILE.into()))
-            .unwrap_or_else(|_| {
-                settings::Settings::system_default(
-                    DEFAULT_SETTINGS_FILE.into(),
-                    DEFAULT_SETTINGS_NAME.into(),
-                )
-            });
+    // the last startup never confirmed that the system survived its settings (e.g. a bad overclock hung it)
+    let startup_failed = utility::applying_sentinel_exists();
+    if startup_failed {
+        log::error!("Settings were being applied when the back-end last stopped, using system defaults instead");
+        if let Err(e) = utility::create_recovery_marker() {
+            log::error!("Failed to create recovery marker: {}", e);
+        }
+    } else if utility::recovery_marker_exists() {
+        log::warn!("Still recovering from a failed startup, using system defaults");
+    }
+    // stays on until the user loads or applies settings, or dismisses the warning
+    let recovering = startup_failed || utility::recovery_marker_exists();
+    let mut loaded_settings = api_worker::load_settings(std::path::Path::new(DEFAULT_SETTINGS_FILE), recovering);
 
     log::info!(
         "Detected device automatically {:?}, using driver: {:?} (This can be overriden)",
@@ -91,13 +109,64 @@ fn main() -> Result<(), ()> {
 
     log::debug!("Settings: {:?}", loaded_settings);
 
-    let (api_handler, api_sender) = crate::api::handler::ApiMessageHandler::new();
+    let (mut api_handler, api_sender) = crate::api::handler::ApiMessageHandler::new();
+    api_handler.set_recovery(recovering);
+    let shared = api_handler.shared();
 
     //let (_save_handle, save_sender) = save_worker::spawn(loaded_settings.clone());
-    let _resume_handle = resume_worker::spawn(api_sender.clone());
-    let _power_handle = power_worker::spawn(api_sender.clone());
+    let (schedule_wake, schedule_wake_rx) = std::sync::mpsc::channel();
+    let schedule_wake_rx = std::sync::Arc::new(std::sync::Mutex::new(schedule_wake_rx));
+    let sender = api_sender.clone();
+    let wake = schedule_wake.clone();
+    supervisor.add("resume_worker", Restart::OnCrash, move || {
+        resume_worker::spawn(sender.clone(), wake.clone())
+    });
+    // set while uevent_worker is sending power events, so that power_worker doesn't need to poll for them
+    let uevents_live = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let sender = api_sender.clone();
+    let live = uevents_live.clone();
+    let watching = shared.watching_temperatures.clone();
+    supervisor.add("power_worker", Restart::OnCrash, move || {
+        power_worker::spawn(sender.clone(), live.clone(), watching.clone())
+    });
+    let sender = api_sender.clone();
+    let enforcing_now = shared.enforcing.clone();
+    supervisor.add("enforce_worker", Restart::OnCrash, move || {
+        enforce_worker::spawn(sender.clone(), enforcing_now.clone())
+    });
+    let sender = api_sender.clone();
+    supervisor.add("uevent_worker", Restart::OnCrash, move || {
+        uevent_worker::spawn(sender.clone(), uevents_live.clone())
+    });
+    let sender = api_sender.clone();
+    supervisor.add("schedule_worker", Restart::OnCrash, move || {
+        schedule_worker::spawn(sender.clone(), schedule_wake_rx.clone())
+    });
+    let sender = api_sender.clone();
+    supervisor.add("game_worker", Restart::OnCrash, move || {
+        game_worker::spawn(sender.clone())
+    });
+    let sender = api_sender.clone();
+    supervisor.add("reload_worker", Restart::OnCrash, move || {
+        reload_worker::spawn(sender.clone())
+    });
+    // nothing has been applied yet, so this is the state to go back to on exit
+    supervisor.watch(
+        "signal_worker",
+        signal_worker::spawn(api_sender.clone(), settings::OriginalState::take()),
+    );
 
-    let (message_getter, message_dismisser) = api::message::MessageHandler::new().to_callables();
+    let mut message_handler = api::message::MessageHandler::new();
+    if recovering {
+        message_handler.set_recovery(shared.recovery.clone());
+        message_handler.push(api::message::ApiMessage {
+            id: None,
+            title: "Settings were not applied".to_owned(),
+            body: "The system did not stay up after PowerTools last applied settings at startup, so system defaults are in use. Check your settings before applying them again.".to_owned(),
+            url: None,
+        });
+    }
+    let (message_getter, message_dismisser) = message_handler.to_callables();
 
     let instance = Instance::new(PORT)
         .register("V_INFO", |_: Vec<Primitive>| {
@@ -209,6 +278,7 @@ fn main() -> Result<(), ()> {
             "CPU_get_governors",
             api::cpu::get_cpu_governors(api_sender.clone()),
         )
+        .register_async("CPU_get_actual", api::cpu::get_actual(api_sender.clone()))
         // gpu API functions
         .register("GPU_set_ppt", api::gpu::set_ppt(api_sender.clone()))
         .register_async("GPU_get_ppt", api::gpu::get_ppt(api_sender.clone()))
@@ -233,6 +303,7 @@ fn main() -> Result<(), ()> {
             "GPU_get_slow_memory",
             api::gpu::get_slow_memory(api_sender.clone()),
         )
+        .register_async("GPU_get_actual", api::gpu::get_actual(api_sender.clone()))
         // general API functions
         .register(
             "GENERAL_set_persistent",
@@ -266,10 +337,38 @@ fn main() -> Result<(), ()> {
             "GENERAL_wait_for_unlocks",
             api::general::lock_unlock_all(api_sender.clone()),
         )
+        .register_async(
+            "GENERAL_wait_for_apply",
+            api::general::wait_for_apply(api_sender.clone()),
+        )
+        .register_async("GENERAL_get_errors", api::general::get_errors(api_sender.clone()))
         .register_blocking(
             "GENERAL_get_limits",
             api::general::get_limits(api_sender.clone()),
         )
+        .register_async(
+            "GENERAL_get_limit_violations",
+            api::general::get_limit_violations(api_sender.clone()),
+        )
+        .register("GENERAL_set_rules", api::general::set_rules(api_sender.clone()))
+        .register_async("GENERAL_get_rules", api::general::get_rules(api_sender.clone()))
+        .register("GENERAL_set_enforce", api::general::set_enforce(api_sender.clone()))
+        .register("GENERAL_get_enforce", api::general::get_enforce(api_sender.clone()))
+        .register("GENERAL_get_schedule", api::general::get_schedule)
+        .register("GENERAL_get_temperatures", api::general::get_temperatures)
+        .register_async(
+            "GENERAL_get_running_game",
+            api::general::get_running_game(api_sender.clone()),
+        )
+        .register("GENERAL_set_schedule", api::general::set_schedule(schedule_wake))
+        .register_async(
+            "GENERAL_get_thermal_status",
+            api::general::get_thermal_status(api_sender.clone()),
+        )
+        .register(
+            "GENERAL_set_thermal_governor",
+            api::general::set_thermal_governor(api_sender.clone()),
+        )
         .register_async(
             "GENERAL_get_provider",
             api::general::get_provider(api_sender.clone()),
@@ -291,9 +390,52 @@ fn main() -> Result<(), ()> {
             "GENERAL_get_periodicals",
             api::general::get_periodicals(api_sender.clone())
         )
+        .register_async(
+            "GENERAL_get_profiles",
+            api::general::get_profiles(api_sender.clone()),
+        )
+        .register_async(
+            "GENERAL_rename_profile",
+            api::general::rename_profile(api_sender.clone()),
+        )
+        .register_async(
+            "GENERAL_duplicate_profile",
+            api::general::duplicate_profile(api_sender.clone()),
+        )
+        .register_async(
+            "GENERAL_delete_profile",
+            api::general::delete_profile(api_sender.clone()),
+        )
+        .register_async(
+            "GENERAL_set_parent",
+            api::general::set_parent(api_sender.clone()),
+        )
+        .register_async(
+            "GENERAL_get_layers",
+            api::general::get_layers(api_sender.clone()),
+        )
+        .register_async(
+            "GENERAL_export_profiles",
+            api::general::export_profiles(api_sender.clone()),
+        )
+        .register_async(
+            "GENERAL_import_profiles",
+            api::general::import_profiles(api_sender.clone()),
+        )
+        .register_async("GENERAL_undo", api::general::undo(api_sender.clone()))
+        .register_async("GENERAL_redo", api::general::redo(api_sender.clone()))
+        .register_async(
+            "GENERAL_history",
+            api::general::get_history(api_sender.clone()),
+        )
+        .register("GENERAL_get_health", api::general::get_health(supervisor.health()))
         .register_async("MESSAGE_get", message_getter)
         .register_async("MESSAGE_dismiss", message_dismisser);
 
+    if let Err(e) = utility::create_applying_sentinel(loaded_settings.general.get_path()) {
+        log::error!("Failed to create applying sentinel: {}", e);
+    }
+    supervisor.add("watchdog_worker", Restart::OnCrash, watchdog_worker::spawn);
     if let Err(e) = loaded_settings.on_set() {
         e.iter()
             .for_each(|e| log::error!("Startup Settings.on_set() error: {}", e));
@@ -305,7 +447,12 @@ fn main() -> Result<(), ()> {
         log::warn!("Failed to change config dir permissions: {}", e);
     }
 
-    api_worker::spawn(loaded_settings, api_handler);
+    let mut first_start = Some((loaded_settings, api_handler));
+    supervisor.add("api_worker", Restart::OnCrash, move || match first_start.take() {
+        Some((settings, handler)) => api_worker::spawn(settings, handler),
+        None => api_worker::respawn(shared.clone()),
+    });
+    let _supervisor_handle = supervisor.spawn();
 
     instance.run_blocking()
 }
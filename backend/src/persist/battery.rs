@@ -3,7 +3,7 @@ use std::default::Default;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BatteryJson {
     pub charge_rate: Option<u64>,
     pub charge_mode: Option<String>,
@@ -13,7 +13,7 @@ pub struct BatteryJson {
     pub root: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BatteryEventJson {
     pub trigger: String,
     pub charge_rate: Option<u64>,
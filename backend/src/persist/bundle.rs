@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+use super::DriverJson;
+
+/// Current version of the profile bundle format
+pub const BUNDLE_VERSION: u64 = 0;
+
+/// Identifies the kind of device some settings were made for
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FingerprintJson {
+    pub cpu_model: Option<String>,
+    pub manufacturer: Option<String>,
+    pub product_name: Option<String>,
+    pub board_name: Option<String>,
+}
+
+impl FingerprintJson {
+    /// Descriptions of how `other` is different from this
+    pub fn differences(&self, other: &Self) -> Vec<String> {
+        let mut diffs = Vec::new();
+        let fields = [
+            ("CPU", &self.cpu_model, &other.cpu_model),
+            ("manufacturer", &self.manufacturer, &other.manufacturer),
+            ("product", &self.product_name, &other.product_name),
+            ("board", &self.board_name, &other.board_name),
+        ];
+        for (name, this, other) in fields {
+            if this != other {
+                diffs.push(format!(
+                    "{} `{}` does not match `{}`",
+                    name,
+                    other.as_deref().unwrap_or("unknown"),
+                    this.as_deref().unwrap_or("unknown")
+                ));
+            }
+        }
+        diffs
+    }
+}
+
+/// Several profiles, for moving them to another device
+#[derive(Serialize, Deserialize)]
+pub struct BundleJson {
+    pub version: u64,
+    /// Driver used on the device the profiles were exported from
+    pub provider: DriverJson,
+    /// Device the profiles were exported from
+    pub fingerprint: FingerprintJson,
+    pub profiles: Vec<BundleProfileJson>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BundleProfileJson {
+    /// Settings filename, e.g. `default_settings.json` or `{id}.json`
+    pub filename: String,
+    /// Complete (i.e. not layered) settings, which may be from an older version
+    pub settings: serde_json::Value,
+}
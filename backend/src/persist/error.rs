@@ -2,6 +2,12 @@
 pub enum JsonError {
     Serde(serde_json::Error),
     Io(std::io::Error),
+    /// Settings file was written by a newer version of PowerTools
+    Version { found: u64, latest: u64 },
+    /// Settings file could not be upgraded from the version it was written with
+    Migration { from: u64, msg: String },
+    /// Parent of a layered profile could not be loaded
+    Parent { parent: String, msg: String },
 }
 
 impl std::fmt::Display for JsonError {
@@ -9,6 +15,19 @@ impl std::fmt::Display for JsonError {
         match self {
             Self::Serde(e) => (e as &dyn std::fmt::Display).fmt(f),
             Self::Io(e) => (e as &dyn std::fmt::Display).fmt(f),
+            Self::Version { found, latest } => write!(
+                f,
+                "settings version {} is newer than the latest supported version {}, please update PowerTools",
+                found, latest
+            ),
+            Self::Migration { from, msg } => write!(
+                f,
+                "failed to migrate settings from version {}: {}",
+                from, msg
+            ),
+            Self::Parent { parent, msg } => {
+                write!(f, "failed to load parent profile {}: {}", parent, msg)
+            }
         }
     }
 }
@@ -2,8 +2,9 @@ use std::default::Default;
 
 use serde::{Deserialize, Serialize};
 
-use super::JsonError;
-use super::{BatteryJson, CpuJson, DriverJson, GpuJson};
+use super::{JsonError, LATEST_VERSION};
+use crate::consts::{SETTINGS_BACKUP_COUNT, SETTINGS_BACKUP_INTERVAL};
+use super::{BatteryJson, CpuJson, DriverJson, GpuJson, RuleJson};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct OnEventJson {
@@ -11,6 +12,16 @@ pub struct OnEventJson {
     pub on_load: Option<String>,
     pub on_set: Option<String>,
     pub on_resume: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_plugged_in: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_unplugged: Option<String>,
+    /// Commands to run when the battery charge crosses a threshold
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_charge: Vec<ChargeEventJson>,
+    /// Maximum run time of event commands, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
 }
 
 impl Default for OnEventJson {
@@ -20,11 +31,22 @@ impl Default for OnEventJson {
             on_load: None,
             on_set: None,
             on_resume: None,
+            on_plugged_in: None,
+            on_unplugged: None,
+            on_charge: Vec::new(),
+            timeout: None,
         }
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChargeEventJson {
+    /// Battery charge percentage, like `>80` or `<20` (same as battery events)
+    pub trigger: String,
+    pub command: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SettingsJson {
     pub version: u64,
     pub name: String,
@@ -34,12 +56,21 @@ pub struct SettingsJson {
     pub battery: BatteryJson,
     pub provider: Option<DriverJson>,
     pub events: Option<OnEventJson>,
+    /// Filename of the profile this one inherits from, relative to this profile's directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// Partial settings changes to apply on power events
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RuleJson>,
+    /// Periodically re-apply settings which something else changed
+    #[serde(default)]
+    pub enforce: bool,
 }
 
 impl Default for SettingsJson {
     fn default() -> Self {
         Self {
-            version: 0,
+            version: LATEST_VERSION,
             name: crate::consts::DEFAULT_SETTINGS_NAME.to_owned(),
             persistent: false,
             cpus: Vec::with_capacity(8),
@@ -47,6 +78,9 @@ impl Default for SettingsJson {
             battery: BatteryJson::default(),
             provider: None,
             events: None,
+            parent: None,
+            rules: Vec::new(),
+            enforce: false,
         }
     }
 }
@@ -56,13 +90,14 @@ impl SettingsJson {
         let path = path.as_ref();
 
         if self.persistent {
-            if let Some(parent) = path.parent() {
-                std::fs::create_dir_all(parent).map_err(JsonError::Io)?;
-            }
-            let mut file = std::fs::File::create(path).map_err(JsonError::Io)?;
-            serde_json::to_writer_pretty(&mut file, &self).map_err(JsonError::Serde)
+            let raw = self.layered(path)?;
+            Self::rotate_backups(path);
+            save_atomically(path, &raw)
         } else {
-            if path.exists() {
+            if Self::is_from_newer_version(path) {
+                log::warn!("Not removing settings {} since it is from a newer version", path.display());
+                Ok(())
+            } else if path.exists() {
                 // remove settings file when persistence is turned off, to prevent it from be loaded next time.
                 std::fs::remove_file(path).map_err(JsonError::Io)
             } else {
@@ -71,14 +106,365 @@ impl SettingsJson {
         }
     }
 
+    /// Open a settings file, upgrading it to the latest version if it was written by an older release.
+    /// The original file is kept as a backup (see [`Self::backup_path`]) when it is upgraded.
+    /// When the settings file is corrupt, the newest valid rotated backup is used instead.
     pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, JsonError> {
+        let path = path.as_ref();
+        match Self::open_inner(path, true) {
+            Err(JsonError::Serde(e)) => {
+                log::error!("Failed to parse settings {}: {}", path.display(), e);
+                for n in 1..=SETTINGS_BACKUP_COUNT {
+                    let backup = Self::rotated_backup_path(path, n);
+                    if !backup.exists() {
+                        continue;
+                    }
+                    match Self::open_inner(&backup, false) {
+                        Ok(settings) => {
+                            log::warn!("Recovered settings {} from backup {}", path.display(), backup.display());
+                            // replace the corrupt file, so that it isn't backed up over the good backups later
+                            if let Err(e) = copy_atomically(&backup, path) {
+                                log::warn!("Failed to restore settings {} from backup {}: {}", path.display(), backup.display(), e);
+                            }
+                            return Ok(settings);
+                        }
+                        Err(e) => log::warn!("Ignoring invalid settings backup {}: {}", backup.display(), e),
+                    }
+                }
+                Err(JsonError::Serde(e))
+            }
+            result => result,
+        }
+    }
+
+    /// Load settings from JSON which may be from an older version, e.g. from a profile bundle
+    pub fn from_raw(mut raw: serde_json::Value) -> Result<Self, JsonError> {
+        super::migrate::migrate(&mut raw)?;
+        serde_json::from_value(raw).map_err(JsonError::Serde)
+    }
+
+    fn open_inner(path: &std::path::Path, backup_before_migration: bool) -> Result<Self, JsonError> {
+        let (raw, version) = Self::open_raw(path)?;
+        let raw = Self::resolve_raw(path, raw, &mut Vec::new())?;
+        let settings = serde_json::from_value(raw).map_err(JsonError::Serde)?;
+        // only back up files which turned out to be settings, not any other JSON file which happens to be nearby
+        // newer versions are rejected by the migration, so any other version is older
+        if backup_before_migration && version != LATEST_VERSION {
+            let backup = Self::backup_path(path, version);
+            if !backup.exists() {
+                if let Err(e) = std::fs::copy(path, &backup) {
+                    log::warn!("Failed to back up settings {} to {}: {}", path.display(), backup.display(), e);
+                } else {
+                    log::info!("Backed up settings {} to {}", path.display(), backup.display());
+                }
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Read and upgrade a settings file, without resolving its parent.
+    /// Returns the upgraded JSON and the version the file was written as.
+    fn open_raw(path: &std::path::Path) -> Result<(serde_json::Value, u64), JsonError> {
+        let mut file = std::fs::File::open(path).map_err(JsonError::Io)?;
+        let mut raw: serde_json::Value = serde_json::from_reader(&mut file).map_err(JsonError::Serde)?;
+        let version = super::migrate::migrate(&mut raw)?;
+        Ok((raw, version))
+    }
+
+    /// Apply the overrides in `raw` on top of its (recursively resolved) parent, if it has one.
+    /// `chain` is the profiles which inherit from this one, to detect cycles.
+    fn resolve_raw(
+        path: &std::path::Path,
+        mut raw: serde_json::Value,
+        chain: &mut Vec<std::path::PathBuf>,
+    ) -> Result<serde_json::Value, JsonError> {
+        let parent = if let Some(parent) = raw.get("parent").and_then(|p| p.as_str()) {
+            parent.to_owned()
+        } else {
+            return Ok(raw);
+        };
+        chain.push(path.to_owned());
+        let mut base = Self::open_parent(path, &parent, chain)?;
+        let profile = super::layers::strip_profile_fields(&mut raw);
+        super::layers::merge(&mut base, raw);
+        if let serde_json::Value::Object(obj) = &mut base {
+            obj.extend(profile);
+        }
+        Ok(base)
+    }
+
+    /// Open the resolved settings of the parent of the profile at `path`, without its profile fields
+    fn open_parent(
+        path: &std::path::Path,
+        parent: &str,
+        chain: &mut Vec<std::path::PathBuf>,
+    ) -> Result<serde_json::Value, JsonError> {
+        let parent_path = path.with_file_name(parent);
+        if chain.contains(&parent_path) {
+            return Err(JsonError::Parent {
+                parent: parent.to_owned(),
+                msg: "profile inherits from itself".to_owned(),
+            });
+        }
+        let to_parent_err = |e: JsonError| JsonError::Parent {
+            parent: parent.to_owned(),
+            msg: e.to_string(),
+        };
+        let (raw, _) = Self::open_raw(&parent_path).map_err(to_parent_err)?;
+        let mut base = Self::resolve_raw(&parent_path, raw, chain).map_err(to_parent_err)?;
+        super::layers::strip_profile_fields(&mut base);
+        Ok(base)
+    }
+
+    /// The resolved settings this profile inherits from, if it has a parent.
+    /// `path` is the location of this profile, which the parent is relative to.
+    pub fn resolve_parent<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> Result<Option<serde_json::Value>, JsonError> {
+        if let Some(parent) = &self.parent {
+            Self::open_parent(path.as_ref(), parent, &mut vec![path.as_ref().to_owned()])
+                .map(|base| Some(Self::normalize(base)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Round-trip resolved settings through `SettingsJson`, so that they look like settings which were
+    /// serialized by this version (e.g. with any missing optional fields filled in) and can be compared to them.
+    /// The settings are left as they are if they aren't valid.
+    fn normalize(mut base: serde_json::Value) -> serde_json::Value {
+        if let serde_json::Value::Object(obj) = &mut base {
+            let defaults = Self::default();
+            obj.insert("version".to_owned(), defaults.version.into());
+            obj.insert("name".to_owned(), defaults.name.into());
+            obj.insert("persistent".to_owned(), defaults.persistent.into());
+        }
+        let normalized = serde_json::from_value::<Self>(base.clone())
+            .and_then(serde_json::to_value);
+        match normalized {
+            Ok(mut normalized) => {
+                super::layers::strip_profile_fields(&mut normalized);
+                normalized
+            }
+            Err(e) => {
+                log::warn!("Comparing to parent settings as they are, since they are invalid: {}", e);
+                super::layers::strip_profile_fields(&mut base);
+                base
+            }
+        }
+    }
+
+    /// Paths of the values this profile overrides, and of the values it inherits from its parent.
+    /// Everything is overridden when there is no parent.
+    pub fn layer_fields<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> Result<(Vec<String>, Vec<String>), JsonError> {
+        let raw = serde_json::to_value(self).map_err(JsonError::Serde)?;
+        let all = super::layers::leaf_paths(&raw);
+        if let Some(base) = self.resolve_parent(path)? {
+            let overridden = super::layers::diff(&base, &raw)
+                .map(|x| super::layers::leaf_paths(&x))
+                .unwrap_or_default();
+            let inherited = all.into_iter().filter(|p| !overridden.contains(p)).collect();
+            Ok((overridden, inherited))
+        } else {
+            Ok((all, Vec::new()))
+        }
+    }
+
+    /// Paths of the values which are different in `other` (profile fields like the name are ignored)
+    pub fn changed_fields(&self, other: &SettingsJson) -> Vec<String> {
+        match (serde_json::to_value(self), serde_json::to_value(other)) {
+            (Ok(this), Ok(other)) => super::layers::diff(&this, &other)
+                .map(|x| super::layers::leaf_paths(&x))
+                .unwrap_or_default(),
+            (Err(e), _) | (_, Err(e)) => {
+                log::error!("Failed to compare settings: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// The JSON to store for this profile: only the overrides when it has a parent, otherwise everything
+    fn layered(&self, path: &std::path::Path) -> Result<serde_json::Value, JsonError> {
+        let mut raw = serde_json::to_value(self).map_err(JsonError::Serde)?;
+        match self.resolve_parent(path) {
+            Ok(Some(base)) => {
+                let profile = super::layers::strip_profile_fields(&mut raw);
+                let mut overrides = super::layers::diff(&base, &raw)
+                    .unwrap_or_else(|| serde_json::Value::Object(Default::default()));
+                if let serde_json::Value::Object(obj) = &mut overrides {
+                    obj.extend(profile);
+                }
+                Ok(overrides)
+            }
+            Ok(None) => Ok(raw),
+            Err(e) => {
+                log::warn!(
+                    "Saving all of settings {} since its parent could not be loaded: {}",
+                    path.display(),
+                    e
+                );
+                Ok(raw)
+            }
+        }
+    }
+
+    /// Shift the rotated backups of a settings file along by one, then back up the current file as the newest.
+    /// Nothing is rotated while the newest backup is younger than `SETTINGS_BACKUP_INTERVAL`,
+    /// so that a burst of saves doesn't replace every backup with (nearly) the same settings.
+    /// Failures are logged but otherwise ignored, since backups must never prevent saving.
+    fn rotate_backups(path: &std::path::Path) {
+        if !path.exists() {
+            return;
+        }
+        let newest_age = std::fs::metadata(Self::rotated_backup_path(path, 1))
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok());
+        if newest_age.map(|age| age < SETTINGS_BACKUP_INTERVAL).unwrap_or(false) {
+            return;
+        }
+        for n in (1..SETTINGS_BACKUP_COUNT).rev() {
+            let older = Self::rotated_backup_path(path, n);
+            if older.exists() {
+                if let Err(e) = std::fs::rename(&older, Self::rotated_backup_path(path, n + 1)) {
+                    log::warn!("Failed to rotate settings backup {}: {}", older.display(), e);
+                }
+            }
+        }
+        let newest = Self::rotated_backup_path(path, 1);
+        if let Err(e) = std::fs::copy(path, &newest) {
+            log::warn!("Failed to back up settings {} to {}: {}", path.display(), newest.display(), e);
+        }
+    }
+
+    /// Location of the `n`th newest rotated backup of a settings file (starting at 1)
+    pub fn rotated_backup_path<P: AsRef<std::path::Path>>(path: P, n: usize) -> std::path::PathBuf {
+        with_suffix(path.as_ref(), &format!(".{}.bak", n))
+    }
+
+    /// Location of the backup of a settings file from before it was migrated from `version`
+    pub fn backup_path<P: AsRef<std::path::Path>>(path: P, version: u64) -> std::path::PathBuf {
+        with_suffix(path.as_ref(), &format!(".v{}.bak", version))
+    }
+
+    /// Whether the settings file exists and was written by a newer, unsupported version
+    pub fn is_from_newer_version<P: AsRef<std::path::Path>>(path: P) -> bool {
+        Self::peek_version(path)
+            .map(|v| v > LATEST_VERSION)
+            .unwrap_or(false)
+    }
+
+    /// Version of a settings file, without loading or upgrading it
+    pub fn peek_version<P: AsRef<std::path::Path>>(path: P) -> Result<u64, JsonError> {
         let mut file = std::fs::File::open(path).map_err(JsonError::Io)?;
-        serde_json::from_reader(&mut file).map_err(JsonError::Serde)
+        let raw: serde_json::Value = serde_json::from_reader(&mut file).map_err(JsonError::Serde)?;
+        Ok(super::migrate::version_of(&raw))
+    }
+}
+
+/// Append `suffix` to the filename of `path`, e.g. `1.json` -> `1.json.tmp`
+fn with_suffix(path: &std::path::Path, suffix: &str) -> std::path::PathBuf {
+    let mut filename = path.file_name().map(|x| x.to_owned()).unwrap_or_default();
+    filename.push(suffix);
+    path.with_file_name(filename)
+}
+
+/// Write `value` to a temporary file first and rename it over `path`,
+/// so that a crash (or power loss) mid-write can't leave a truncated file behind
+pub(super) fn save_atomically<T: Serialize>(path: &std::path::Path, value: &T) -> Result<(), JsonError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(JsonError::Io)?;
+    }
+    let tmp_path = with_suffix(path, ".tmp");
+    let mut file = std::fs::File::create(&tmp_path).map_err(JsonError::Io)?;
+    serde_json::to_writer_pretty(&mut file, value).map_err(JsonError::Serde)?;
+    file.sync_all().map_err(JsonError::Io)?;
+    drop(file);
+    rename_synced(&tmp_path, path)
+}
+
+/// Replace `path` with a copy of `from`, the same way as `save_atomically()`
+fn copy_atomically(from: &std::path::Path, path: &std::path::Path) -> Result<(), JsonError> {
+    let tmp_path = with_suffix(path, ".tmp");
+    std::fs::copy(from, &tmp_path).map_err(JsonError::Io)?;
+    std::fs::File::open(&tmp_path)
+        .and_then(|file| file.sync_all())
+        .map_err(JsonError::Io)?;
+    rename_synced(&tmp_path, path)
+}
+
+/// Rename `from` to `to`, then sync their directory so that the rename itself survives a power loss
+fn rename_synced(from: &std::path::Path, to: &std::path::Path) -> Result<(), JsonError> {
+    std::fs::rename(from, to).map_err(JsonError::Io)?;
+    if let Some(parent) = to.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::File::open(parent)
+            .and_then(|dir| dir.sync_all())
+            .map_err(JsonError::Io)?;
     }
+    Ok(())
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MinMaxJson<T> {
     pub max: Option<T>,
     pub min: Option<T>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recover_from_backup_test() {
+        let dir = std::env::temp_dir().join(format!("powertools-recover-backup-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("1234.json");
+        let mut settings = SettingsJson {
+            name: "Backed up".to_owned(),
+            persistent: true,
+            ..Default::default()
+        };
+        settings.save(&path).unwrap();
+        settings.name = "Newer".to_owned();
+        settings.save(&path).unwrap();
+        assert!(SettingsJson::rotated_backup_path(&path, 1).exists());
+        // a second save soon after doesn't rotate again
+        settings.save(&path).unwrap();
+        assert!(!SettingsJson::rotated_backup_path(&path, 2).exists());
+
+        std::fs::write(&path, "{\"version\": 0, \"name\": ").unwrap();
+        let recovered = SettingsJson::open(&path).unwrap();
+        assert_eq!(recovered.name, "Backed up");
+        // the corrupt file was replaced by the backup
+        assert_eq!(SettingsJson::open_inner(&path, false).unwrap().name, "Backed up");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unchanged_child_saves_no_overrides_test() {
+        let dir = std::env::temp_dir().join(format!("powertools-unchanged-child-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // written by an older release, so optional fields are missing
+        std::fs::write(
+            dir.join("parent.json"),
+            r#"{"version": 0, "name": "Parent", "persistent": true, "cpus": [], "gpu": {"slow_memory": false}, "battery": {}}"#,
+        )
+        .unwrap();
+        let path = dir.join("child.json");
+        let child = SettingsJson {
+            persistent: true,
+            parent: Some("parent.json".to_owned()),
+            ..Default::default()
+        };
+        child.save(&path).unwrap();
+
+        let mut raw: serde_json::Value = serde_json::from_reader(std::fs::File::open(&path).unwrap()).unwrap();
+        super::super::layers::strip_profile_fields(&mut raw);
+        assert_eq!(raw, serde_json::json!({}));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
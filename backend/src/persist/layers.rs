@@ -0,0 +1,165 @@
+//! Layered profiles: a profile with a parent only stores the values which differ from its parent.
+//!
+//! Layering is done on the raw JSON, where an absent field means "inherit from parent"
+//! and any present field (including `null`) overrides the parent's value.
+//! Arrays of the same length are layered element by element (e.g. per-CPU settings),
+//! while arrays of a different length replace the parent's array entirely.
+
+use serde_json::{Map, Value};
+
+/// Fields which describe the profile itself, so they always belong to the child
+pub const PROFILE_FIELDS: &[&str] = &["version", "name", "persistent", "parent"];
+
+/// Apply `overrides` on top of `base`
+pub fn merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(base), Value::Array(overrides)) if base.len() == overrides.len() => {
+            for (base_value, value) in base.iter_mut().zip(overrides) {
+                merge(base_value, value);
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+/// The minimal overrides which turn `base` into `full`, or `None` if they're identical.
+/// `merge(base, diff(base, full))` results in `full`.
+pub fn diff(base: &Value, full: &Value) -> Option<Value> {
+    match (base, full) {
+        (Value::Object(base), Value::Object(full)) => {
+            let mut overrides = Map::new();
+            for (key, value) in full {
+                let changed = match base.get(key) {
+                    Some(base_value) => diff(base_value, value),
+                    None => Some(value.clone()),
+                };
+                if let Some(changed) = changed {
+                    overrides.insert(key.to_owned(), changed);
+                }
+            }
+            if overrides.is_empty() {
+                None
+            } else {
+                Some(Value::Object(overrides))
+            }
+        }
+        (Value::Array(base), Value::Array(full)) if base.len() == full.len() => {
+            if base == full {
+                return None;
+            }
+            // only objects can be left empty to inherit the parent's element
+            if !base.iter().chain(full.iter()).all(Value::is_object) {
+                return Some(Value::Array(full.clone()));
+            }
+            Some(Value::Array(
+                base.iter()
+                    .zip(full.iter())
+                    .map(|(b, f)| diff(b, f).unwrap_or_else(|| Value::Object(Map::new())))
+                    .collect(),
+            ))
+        }
+        (base, full) => {
+            if base == full {
+                None
+            } else {
+                Some(full.clone())
+            }
+        }
+    }
+}
+
+/// Paths (e.g. `gpu.fast_ppt` or `cpus.0.governor`) of every setting value in `settings`
+pub fn leaf_paths(settings: &Value) -> Vec<String> {
+    let mut paths = Vec::new();
+    collect_paths(settings, String::new(), &mut paths);
+    paths
+}
+
+fn collect_paths(value: &Value, prefix: String, paths: &mut Vec<String>) {
+    let join = |key: &dyn std::fmt::Display| {
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", prefix, key)
+        }
+    };
+    match value {
+        // an empty object has no values of its own (e.g. a CPU which inherits everything)
+        Value::Object(obj) => {
+            for (key, value) in obj {
+                if prefix.is_empty() && PROFILE_FIELDS.contains(&key.as_str()) {
+                    continue;
+                }
+                collect_paths(value, join(key), paths);
+            }
+        }
+        Value::Array(arr) if !arr.is_empty() && arr.iter().all(Value::is_object) => {
+            for (i, value) in arr.iter().enumerate() {
+                collect_paths(value, join(&i), paths);
+            }
+        }
+        _ => {
+            if !prefix.is_empty() {
+                paths.push(prefix);
+            }
+        }
+    }
+}
+
+/// Remove the profile fields from the top level of some settings, leaving only the layerable values
+pub fn strip_profile_fields(settings: &mut Value) -> Map<String, Value> {
+    let mut removed = Map::new();
+    if let Value::Object(obj) = settings {
+        for field in PROFILE_FIELDS {
+            if let Some(value) = obj.remove(*field) {
+                removed.insert((*field).to_owned(), value);
+            }
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_merge_roundtrip_test() {
+        let base = serde_json::json!({
+            "cpus": [{ "online": true, "governor": "schedutil" }, { "online": true, "governor": "schedutil" }],
+            "gpu": { "fast_ppt": 15000000, "slow_ppt": null, "slow_memory": false },
+            "battery": { "events": [] },
+        });
+        let full = serde_json::json!({
+            "cpus": [{ "online": true, "governor": "schedutil" }, { "online": false, "governor": "schedutil" }],
+            "gpu": { "fast_ppt": null, "slow_ppt": null, "slow_memory": false },
+            "battery": { "events": [] },
+        });
+        let overrides = diff(&base, &full).unwrap();
+        assert_eq!(
+            overrides,
+            serde_json::json!({
+                "cpus": [{}, { "online": false }],
+                "gpu": { "fast_ppt": null },
+            })
+        );
+        assert_eq!(
+            leaf_paths(&overrides),
+            vec!["cpus.1.online".to_owned(), "gpu.fast_ppt".to_owned()]
+        );
+        let mut merged = base.clone();
+        merge(&mut merged, overrides);
+        assert_eq!(merged, full);
+        assert!(diff(&full, &full).is_none());
+    }
+}
@@ -0,0 +1,77 @@
+//! Step-by-step upgrades of settings files written by older releases.
+//!
+//! Migrations operate on the raw JSON, before it is deserialized into [`super::SettingsJson`],
+//! so that they can cope with fields which no longer exist in the current structs.
+
+use serde_json::Value;
+
+use super::JsonError;
+
+/// Current version of the settings file format
+pub const LATEST_VERSION: u64 = 0;
+
+type Migration = fn(&mut serde_json::Map<String, Value>) -> Result<(), String>;
+
+/// Migrations, in order; the migration at index `n` upgrades version `n` to version `n + 1`
+const MIGRATIONS: &[Migration] = &[];
+
+/// Version of a raw settings file (files from before versioning was introduced have none)
+pub fn version_of(settings: &Value) -> u64 {
+    settings.get("version").and_then(|v| v.as_u64()).unwrap_or(0)
+}
+
+/// Upgrade raw settings JSON to the latest version.
+/// Returns the version the settings were upgraded from.
+pub fn migrate(settings: &mut Value) -> Result<u64, JsonError> {
+    let original = version_of(settings);
+    if original > LATEST_VERSION {
+        return Err(JsonError::Version {
+            found: original,
+            latest: LATEST_VERSION,
+        });
+    }
+    let obj = settings.as_object_mut().ok_or_else(|| JsonError::Migration {
+        from: original,
+        msg: "settings is not a JSON object".to_owned(),
+    })?;
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(original as usize) {
+        let version = version as u64;
+        log::info!("Migrating settings from version {} to {}", version, version + 1);
+        migration(obj).map_err(|msg| JsonError::Migration { from: version, msg })?;
+        obj.insert("version".to_owned(), Value::from(version + 1));
+    }
+    Ok(original)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_v0_test() {
+        let mut settings = serde_json::json!({
+            "version": 0,
+            "name": "Main",
+            "persistent": true,
+            "cpus": [],
+            "gpu": { "fast_ppt": null, "slow_ppt": null, "clock_limits": null, "slow_memory": false },
+            "battery": { "charge_rate": null, "charge_mode": null },
+            "provider": "gabe-boy"
+        });
+        let original = settings.clone();
+        assert_eq!(migrate(&mut settings).unwrap(), 0);
+        assert_eq!(version_of(&settings), LATEST_VERSION);
+        assert_eq!(settings, original);
+        let parsed: super::super::SettingsJson = serde_json::from_value(settings).unwrap();
+        assert_eq!(parsed.version, LATEST_VERSION);
+    }
+
+    #[test]
+    fn migrate_newer_test() {
+        let mut settings = serde_json::json!({ "version": LATEST_VERSION + 1 });
+        assert!(matches!(
+            migrate(&mut settings),
+            Err(JsonError::Version { .. })
+        ));
+    }
+}
@@ -1,14 +1,27 @@
 mod battery;
+mod bundle;
 mod cpu;
 mod driver;
 mod error;
 mod general;
 mod gpu;
+mod layers;
+mod migrate;
+mod profiles;
+mod rule;
+mod schedule;
+mod thermal;
 
 pub use battery::{BatteryEventJson, BatteryJson};
+pub use bundle::{BundleJson, BundleProfileJson, FingerprintJson, BUNDLE_VERSION};
 pub use cpu::CpuJson;
 pub use driver::DriverJson;
 pub use general::{MinMaxJson, OnEventJson, SettingsJson};
 pub use gpu::GpuJson;
+pub use migrate::LATEST_VERSION;
+pub use profiles::{is_profile_filename, list_profiles, profile_filename};
+pub use rule::{PartialSettingsJson, RuleJson};
+pub use schedule::{ScheduleEntryJson, ScheduleJson, ScheduleProfileJson, ScheduleStateJson};
+pub use thermal::ThermalGovernorJson;
 
 pub use error::JsonError;
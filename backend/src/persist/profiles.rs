@@ -0,0 +1,95 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::{JsonError, SettingsJson};
+
+/// Settings file found in the settings directory
+pub struct ProfileFile {
+    pub path: PathBuf,
+    /// Numeric identifier (usually the app id), if the filename is one
+    pub id: Option<u64>,
+    pub settings: SettingsJson,
+    pub modified: Option<SystemTime>,
+}
+
+/// Files in the settings directory which are not profiles
+const NON_PROFILE_FILES: &[&str] = &[
+    crate::consts::LIMITS_FILE,
+    crate::consts::SCHEDULE_FILE,
+    crate::consts::SCHEDULE_STATE_FILE,
+    crate::consts::THERMAL_GOVERNOR_FILE,
+];
+
+/// Whether `filename` may be a profile: a plain `*.json` file name which isn't used for something else
+pub fn is_profile_filename(filename: &str) -> bool {
+    Path::new(filename).file_name().map(|f| f == filename).unwrap_or(false)
+        && filename.ends_with(".json")
+        && !NON_PROFILE_FILES.contains(&filename)
+}
+
+#[inline]
+pub fn profile_filename(id: u64) -> String {
+    format!("{}.json", id)
+}
+
+/// Find all settings files in `dir`.
+/// Files which are not valid settings (e.g. the limits cache) are skipped, without being backed up or migrated.
+pub fn list_profiles<P: AsRef<Path>>(dir: P) -> Result<Vec<ProfileFile>, JsonError> {
+    let mut profiles = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(JsonError::Io)? {
+        let entry = entry.map_err(JsonError::Io)?;
+        let path = entry.path();
+        let is_profile = path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .map(is_profile_filename)
+            .unwrap_or(false);
+        if !path.is_file() || !is_profile {
+            continue;
+        }
+        match SettingsJson::open(&path) {
+            Ok(settings) => profiles.push(ProfileFile {
+                id: path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse().ok()),
+                modified: entry.metadata().and_then(|meta| meta.modified()).ok(),
+                path,
+                settings,
+            }),
+            Err(e) => log::debug!("Ignoring non-settings file {}: {}", path.display(), e),
+        }
+    }
+    Ok(profiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_profile_filename_test() {
+        assert!(is_profile_filename("1234.json"));
+        assert!(is_profile_filename(crate::consts::DEFAULT_SETTINGS_FILE));
+        assert!(!is_profile_filename(crate::consts::THERMAL_GOVERNOR_FILE));
+        assert!(!is_profile_filename(crate::consts::SCHEDULE_FILE));
+        assert!(!is_profile_filename(crate::consts::SCHEDULE_STATE_FILE));
+        assert!(!is_profile_filename(crate::consts::LIMITS_FILE));
+        assert!(!is_profile_filename("../1234.json"));
+        assert!(!is_profile_filename("1234.json.1.bak"));
+    }
+
+    #[test]
+    fn list_profiles_skips_other_json_test() {
+        let dir = std::env::temp_dir().join(format!("powertools-list-profiles-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(crate::consts::THERMAL_GOVERNOR_FILE), "{\"enabled\": false}").unwrap();
+        std::fs::write(dir.join("other.json"), "[1, 2, 3]").unwrap();
+        let profiles = list_profiles(&dir).unwrap();
+        assert!(profiles.is_empty());
+        // no migration backups of files which aren't settings
+        assert!(!SettingsJson::backup_path(dir.join("other.json"), 0).exists());
+        assert!(!SettingsJson::backup_path(dir.join(crate::consts::THERMAL_GOVERNOR_FILE), 0).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
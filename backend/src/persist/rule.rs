@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use super::MinMaxJson;
+
+/// Partial settings change which is applied when a power event happens
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RuleJson {
+    /// Same format as battery events: `plug-in`, `plug-out`, `>80`, `<20`, etc.
+    /// or a temperature in Celsius like `temp>85`
+    pub trigger: String,
+    /// Name of the hwmon device for temperature triggers (e.g. `amdgpu`); the hottest sensor when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sensor: Option<String>,
+    /// Degrees Celsius below the threshold at which a temperature rule's changes are undone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hysteresis: Option<f64>,
+    #[serde(flatten)]
+    pub changes: PartialSettingsJson,
+}
+
+/// Change to some settings; unset fields are left alone
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PartialSettingsJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fast_ppt: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slow_ppt: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gpu_clock_limits: Option<MinMaxJson<u64>>,
+    /// Online state of each CPU, in order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpus_online: Option<Vec<bool>>,
+    /// Governor for all CPUs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub governor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smt: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub charge_rate: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub charge_mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub charge_limit: Option<f64>,
+}
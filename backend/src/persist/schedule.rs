@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+use super::{JsonError, PartialSettingsJson};
+
+pub const SCHEDULE_VERSION: u64 = 0;
+
+/// Profile switches at certain times of day
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScheduleJson {
+    pub version: u64,
+    #[serde(default)]
+    pub entries: Vec<ScheduleEntryJson>,
+}
+
+/// Scheduled change, which stays in effect until the next entry starts
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScheduleEntryJson {
+    /// Local time of day, like `22:30`
+    pub start: String,
+    /// Days of the week (0 = Sunday); every day when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<u8>,
+    /// Profile to load
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<ScheduleProfileJson>,
+    /// Changes to apply after loading the profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<PartialSettingsJson>,
+}
+
+/// Profile which a schedule entry loads
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleProfileJson {
+    Main,
+    Game {
+        /// App id
+        id: u64,
+        /// Name of the profile, in case it doesn't exist yet
+        #[serde(default)]
+        name: String,
+    },
+}
+
+/// Schedule entry which was applied last, so that it isn't applied again after a restart
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduleStateJson {
+    /// Index of the entry in the schedule
+    pub entry: usize,
+    /// When the entry started, as a unix timestamp
+    pub start: i64,
+}
+
+impl Default for ScheduleJson {
+    fn default() -> Self {
+        Self {
+            version: SCHEDULE_VERSION,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl ScheduleJson {
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, JsonError> {
+        let file = std::fs::File::open(path).map_err(JsonError::Io)?;
+        serde_json::from_reader(file).map_err(JsonError::Serde)
+    }
+
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), JsonError> {
+        super::general::save_atomically(path.as_ref(), self)
+    }
+}
+
+impl ScheduleStateJson {
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, JsonError> {
+        let file = std::fs::File::open(path).map_err(JsonError::Io)?;
+        serde_json::from_reader(file).map_err(JsonError::Serde)
+    }
+
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), JsonError> {
+        super::general::save_atomically(path.as_ref(), self)
+    }
+}
+
+impl ScheduleEntryJson {
+    /// Minutes since midnight, if `start` is a valid time
+    pub fn start_minutes(&self) -> Option<u32> {
+        let (hours, minutes) = self.start.trim().split_once(':')?;
+        let hours: u32 = hours.parse().ok()?;
+        let minutes: u32 = minutes.parse().ok()?;
+        if hours < 24 && minutes < 60 {
+            Some(hours * 60 + minutes)
+        } else {
+            None
+        }
+    }
+
+    pub fn applies_on(&self, weekday: u8) -> bool {
+        self.days.is_empty() || self.days.contains(&weekday)
+    }
+}
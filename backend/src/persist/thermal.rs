@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+use super::JsonError;
+
+/// Configuration of the built-in thermal safety governor
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ThermalGovernorJson {
+    /// Off unless the user turns it on, since the ceilings can't suit every device
+    pub enabled: bool,
+    /// Highest APU temperature (Celsius) before PPT and clocks are lowered
+    pub apu_ceiling: f64,
+    /// Highest battery temperature (Celsius) before PPT and clocks are lowered
+    pub battery_ceiling: f64,
+    /// Degrees Celsius below the ceilings before throttling is backed off
+    pub hysteresis: f64,
+}
+
+impl Default for ThermalGovernorJson {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            apu_ceiling: 95.0,
+            battery_ceiling: 50.0,
+            hysteresis: 5.0,
+        }
+    }
+}
+
+impl ThermalGovernorJson {
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, JsonError> {
+        let file = std::fs::File::open(path).map_err(JsonError::Io)?;
+        serde_json::from_reader(file).map_err(JsonError::Serde)
+    }
+
+    /// Open the configuration, falling back to the defaults if there is none (or it is invalid)
+    pub fn open_or_default<P: AsRef<std::path::Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        match Self::open(path) {
+            Ok(x) => x,
+            Err(JsonError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                log::error!("Failed to open {}, using default thermal limits: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), JsonError> {
+        super::general::save_atomically(path.as_ref(), self)
+    }
+}
@@ -1,21 +1,56 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Sender;
+use std::sync::Arc;
 use std::thread::{self, JoinHandle};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::api::handler::ApiMessage;
-//use crate::utility::unwrap_maybe_fatal;
 
-const PERIOD: Duration = Duration::from_secs(5);
+const POWER_PERIOD: Duration = Duration::from_secs(5);
+/// Battery charge polling while uevent_worker is sending power events, in case it misses some
+const BACKSTOP_PERIOD: Duration = Duration::from_secs(60);
+const TEMPERATURE_PERIOD: Duration = Duration::from_secs(10);
 
-pub fn spawn(sender: Sender<ApiMessage>) -> JoinHandle<()> {
+/// Poll power supplies every `POWER_PERIOD` (or only battery charge every `BACKSTOP_PERIOD`, while `uevents_live`)
+/// and temperatures every `TEMPERATURE_PERIOD`, but only while `watching_temperatures`.
+/// Temperature checks start at the next power poll after `watching_temperatures` is set.
+pub fn spawn(
+    sender: Sender<ApiMessage>,
+    uevents_live: Arc<AtomicBool>,
+    watching_temperatures: Arc<AtomicBool>,
+) -> JoinHandle<()> {
     thread::spawn(move || {
         log::info!("power_worker starting...");
+        let start = Instant::now();
+        let mut last_power: Option<Instant> = None;
+        let mut next_temperature = start;
         loop {
-            sender
-                .send(ApiMessage::PowerVibeCheck)
-                .expect("power_worker send failed");
-            thread::sleep(PERIOD);
+            let now = Instant::now();
+            let live = uevents_live.load(Ordering::SeqCst);
+            let power_period = if live { BACKSTOP_PERIOD } else { POWER_PERIOD };
+            let next_power = last_power.map(|x| x + power_period).unwrap_or(start);
+            if now >= next_power {
+                if sender.send(ApiMessage::PowerVibeCheck(live)).is_err() {
+                    break;
+                }
+                last_power = Some(now);
+            }
+            let watching = watching_temperatures.load(Ordering::SeqCst);
+            if watching && now >= next_temperature {
+                if sender.send(ApiMessage::CheckTemperatures).is_err() {
+                    break;
+                }
+                next_temperature = now + TEMPERATURE_PERIOD;
+            }
+            // waking up for temperatures picks up changes to `uevents_live` too
+            let next_power = last_power.map(|x| x + power_period).unwrap_or(now);
+            let next_wake = if watching {
+                next_power.min(next_temperature)
+            } else {
+                next_power
+            };
+            thread::sleep(next_wake.saturating_duration_since(now));
         }
-        //log::warn!("resume_worker completed!");
+        log::info!("power_worker stopping, api_worker is gone");
     })
 }
@@ -0,0 +1,87 @@
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::api::handler::ApiMessage;
+
+const RETRY_PERIOD: Duration = Duration::from_secs(5);
+
+pub fn spawn(sender: Sender<ApiMessage>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        log::info!("reload_worker starting...");
+        let dir = crate::utility::settings_dir();
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            log::warn!("reload_worker failed to create {}: {}", dir.display(), e);
+        }
+        let mut watcher = match Watcher::new(&dir) {
+            Ok(x) => x,
+            Err(e) => {
+                log::error!(
+                    "reload_worker failed to watch {}, external changes to settings will not be noticed: {}",
+                    dir.display(),
+                    e
+                );
+                return;
+            }
+        };
+        loop {
+            match watcher.read_names() {
+                Ok(names) => {
+                    for name in names {
+                        if is_settings_file(&name) {
+                            log::debug!("reload_worker noticed change to {:?}", name);
+                            let message = ApiMessage::SettingsFileChanged(PathBuf::from(name));
+                            if sender.send(message).is_err() {
+                                log::info!("reload_worker stopping, api_worker is gone");
+                                return;
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    log::error!("reload_worker failed to read file events: {}", e);
+                    thread::sleep(RETRY_PERIOD);
+                }
+            }
+        }
+        //log::warn!("reload_worker completed!");
+    })
+}
+
+/// Only actual settings files, not temporary files or backups created while saving
+fn is_settings_file(name: &OsStr) -> bool {
+    Path::new(name)
+        .extension()
+        .map(|ext| ext == "json")
+        .unwrap_or(false)
+}
+
+/// inotify watch on a single directory
+struct Watcher {
+    inotify: inotify::Inotify,
+    buf: [u8; 4096],
+}
+
+impl Watcher {
+    fn new(dir: &Path) -> std::io::Result<Self> {
+        let inotify = inotify::Inotify::init()?;
+        // files are saved by renaming a temporary file over them, but editors may also write in place
+        inotify
+            .watches()
+            .add(dir, inotify::WatchMask::CLOSE_WRITE | inotify::WatchMask::MOVED_TO)?;
+        Ok(Self {
+            inotify,
+            buf: [0; 4096],
+        })
+    }
+
+    /// Block until some files change, then return their names
+    fn read_names(&mut self) -> std::io::Result<Vec<OsString>> {
+        let events = self.inotify.read_events_blocking(&mut self.buf)?;
+        Ok(events
+            .filter_map(|event| event.name.map(|name| name.to_owned()))
+            .collect())
+    }
+}
@@ -1,36 +1,167 @@
+use std::io::BufRead;
+use std::process::{Command, Stdio};
 use std::sync::mpsc::Sender;
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
 use crate::api::handler::ApiMessage;
-//use crate::utility::unwrap_maybe_fatal;
 
 const ALLOWED_ERROR: f64 = 20.0; // period of 50ms with 20x means sleep has to be >= 1s to be detected
 
-pub fn spawn(sender: Sender<ApiMessage>) -> JoinHandle<()> {
+/// How often the clocks are compared when logind isn't available
+const CLOCK_PERIOD: Duration = Duration::from_millis(250);
+/// Shortest time asleep which counts as a suspend (the clocks drift apart by less while awake)
+const MIN_SLEEP: Duration = Duration::from_millis(100);
+
+const LOGIND_MATCH: &str = "type='signal',interface='org.freedesktop.login1.Manager',member='PrepareForSleep'";
+
+/// `resumed` is notified on resume too, for other workers which care about time spent asleep.
+///
+/// Resume is detected from logind's `PrepareForSleep` signal when possible. Otherwise the time
+/// spent asleep is measured as the difference between `CLOCK_BOOTTIME` and `CLOCK_MONOTONIC`,
+/// with the original timing drift detection as a last resort.
+pub fn spawn(sender: Sender<ApiMessage>, resumed: Sender<()>) -> JoinHandle<()> {
     thread::spawn(move || {
         log::info!("resume_worker starting...");
-        let duration = Duration::from_millis(50); // very low so it detects before Steam client does
-                                                  // this allows PowerTools to set some values at wakeup and Steam to override them before user notices
-        let mut start = Instant::now();
-        loop {
-            let old_start = start.elapsed();
-            start = Instant::now();
-            if old_start.as_secs_f64() > duration.as_secs_f64() * (1.0 + ALLOWED_ERROR) {
-                // has just resumed from sleep
-                log::info!("Resume detected");
-                sender
-                    .send(ApiMessage::OnResume)
-                    .expect("resume_worker send failed");
-                log::debug!(
-                    "OnResume completed after sleeping for {}s",
-                    old_start.as_secs_f32()
-                );
-            } else {
-                log::debug!("OnResume got sleep period of {}s", old_start.as_secs_f32());
+        // false once api_worker is gone, i.e. when shutting down
+        let on_resume = move || {
+            if sender.send(ApiMessage::OnResume).is_err() {
+                return false;
+            }
+            if resumed.send(()).is_err() {
+                log::debug!("resume_worker resume listener is gone");
             }
-            thread::sleep(duration);
+            true
+        };
+        match watch_logind(&on_resume) {
+            Ok(()) => {
+                log::info!("resume_worker stopping, api_worker is gone");
+                return;
+            }
+            Err(e) => log::warn!("resume_worker cannot use logind, falling back to polling: {}", e),
+        }
+        if time_asleep().is_some() {
+            poll_clocks(&on_resume);
+        } else {
+            log::warn!("resume_worker cannot read CLOCK_BOOTTIME, falling back to timing drift");
+            poll_drift(&on_resume);
         }
-        //log::warn!("resume_worker completed!");
+        log::info!("resume_worker stopping, api_worker is gone");
     })
 }
+
+/// Wait for logind's `PrepareForSleep(false)`, which is sent right after resuming.
+/// This only returns if monitoring the system bus fails, or with `Ok` once `on_resume` returns false.
+fn watch_logind(on_resume: &impl Fn() -> bool) -> Result<(), String> {
+    let mut child = Command::new("busctl")
+        .args(["monitor", "--system", "--json=short", "--match", LOGIND_MATCH])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("failed to start busctl: {}", e))?;
+    let stdout = child.stdout.take().ok_or("busctl has no stdout")?;
+    for line in std::io::BufReader::new(stdout).lines() {
+        let line = line.map_err(|e| format!("failed to read busctl output: {}", e))?;
+        match prepare_for_sleep(&line) {
+            Some(true) => log::info!("Suspend signalled by logind"),
+            Some(false) => {
+                log::info!("Resume detected (logind)");
+                if !on_resume() {
+                    let _ = child.kill();
+                    return Ok(());
+                }
+            }
+            None => {}
+        }
+    }
+    let status = child.wait().map_err(|e| format!("failed to wait for busctl: {}", e))?;
+    Err(format!("busctl exited with {}", status))
+}
+
+/// Argument of a `PrepareForSleep` signal, from a line of `busctl monitor --json=short` output
+fn prepare_for_sleep(line: &str) -> Option<bool> {
+    let message: serde_json::Value = serde_json::from_str(line).ok()?;
+    if message.get("member")?.as_str()? != "PrepareForSleep" {
+        return None;
+    }
+    message.get("payload")?.get("data")?.get(0)?.as_bool()
+}
+
+/// Time spent suspended since boot.
+/// `CLOCK_BOOTTIME` keeps counting while suspended and `CLOCK_MONOTONIC` doesn't, so this only grows on suspend.
+fn time_asleep() -> Option<Duration> {
+    use rustix::time::{clock_gettime_dynamic, ClockId, DynamicClockId};
+    let read = |clock| {
+        clock_gettime_dynamic(clock)
+            .ok()
+            .map(|ts| Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+    };
+    let monotonic = read(DynamicClockId::Known(ClockId::Monotonic))?;
+    let boottime = read(DynamicClockId::Boottime)?;
+    Some(boottime.saturating_sub(monotonic))
+}
+
+/// Detect resume from `CLOCK_BOOTTIME` moving ahead of `CLOCK_MONOTONIC`.
+/// Unlike timing drift, this isn't fooled by the thread being starved under heavy load.
+fn poll_clocks(on_resume: &impl Fn() -> bool) {
+    let mut last_asleep = time_asleep().unwrap_or_default();
+    loop {
+        thread::sleep(CLOCK_PERIOD);
+        let asleep = if let Some(x) = time_asleep() {
+            x
+        } else {
+            continue;
+        };
+        let slept = asleep.saturating_sub(last_asleep);
+        last_asleep = asleep;
+        if slept >= MIN_SLEEP {
+            log::info!("Resume detected after sleeping for {}s", slept.as_secs_f32());
+            if !on_resume() {
+                return;
+            }
+        }
+    }
+}
+
+/// Detect resume from the thread sleeping for much longer than it asked to
+fn poll_drift(on_resume: &impl Fn() -> bool) {
+    let duration = Duration::from_millis(50); // very low so it detects before Steam client does
+                                              // this allows PowerTools to set some values at wakeup and Steam to override them before user notices
+    let mut start = Instant::now();
+    loop {
+        let old_start = start.elapsed();
+        start = Instant::now();
+        if old_start.as_secs_f64() > duration.as_secs_f64() * (1.0 + ALLOWED_ERROR) {
+            // has just resumed from sleep
+            log::info!("Resume detected");
+            if !on_resume() {
+                return;
+            }
+            log::debug!(
+                "OnResume completed after sleeping for {}s",
+                old_start.as_secs_f32()
+            );
+        } else {
+            log::debug!("OnResume got sleep period of {}s", old_start.as_secs_f32());
+        }
+        thread::sleep(duration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prepare_for_sleep_test() {
+        let resume = r#"{"type":"signal","sender":":1.3","path":"/org/freedesktop/login1","interface":"org.freedesktop.login1.Manager","member":"PrepareForSleep","payload":{"type":"b","data":[false]}}"#;
+        assert_eq!(prepare_for_sleep(resume), Some(false));
+        let suspend = resume.replace("false", "true");
+        assert_eq!(prepare_for_sleep(&suspend), Some(true));
+        let other = resume.replace("PrepareForSleep", "PrepareForShutdown");
+        assert_eq!(prepare_for_sleep(&other), None);
+        assert_eq!(prepare_for_sleep("Monitoring bus message stream."), None);
+        assert!(time_asleep().is_some());
+    }
+}
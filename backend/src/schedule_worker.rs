@@ -0,0 +1,181 @@
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use chrono::{Datelike, Days, Local, TimeZone};
+
+use crate::api::handler::ApiMessage;
+use crate::persist::{JsonError, ScheduleEntryJson, ScheduleJson, ScheduleProfileJson, ScheduleStateJson};
+
+const PERIOD: Duration = Duration::from_secs(30);
+
+/// Check the schedule every `PERIOD`, and whenever something is sent to `wake` (i.e. on resume or a schedule change).
+/// Time spent asleep doesn't count towards `PERIOD`, so missed boundaries are applied right after resuming.
+/// `wake` is shared so that the worker can be restarted.
+pub fn spawn(sender: Sender<ApiMessage>, wake: Arc<Mutex<Receiver<()>>>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        log::info!("schedule_worker starting...");
+        // a crashed worker may have poisoned the lock, but the receiver is still fine
+        let wake = wake.lock().unwrap_or_else(|e| e.into_inner());
+        let path = crate::utility::settings_dir().join(crate::consts::SCHEDULE_FILE);
+        let state_path = crate::utility::settings_dir().join(crate::consts::SCHEDULE_STATE_FILE);
+        // (entry index, start time) of the entry which was last applied, possibly before a restart
+        let mut last_applied: Option<(usize, i64)> = ScheduleStateJson::open(&state_path)
+            .ok()
+            .map(|state| (state.entry, state.start));
+        let mut last_error: Option<String> = None;
+        let mut starting = true;
+        loop {
+            match ScheduleJson::open(&path) {
+                Ok(schedule) => {
+                    last_error = None;
+                    let active = active_entry(&schedule.entries, now());
+                    if let Some((index, start)) = active {
+                        let entry = &schedule.entries[index];
+                        if last_applied != active {
+                            log::info!(
+                                "Applying schedule entry #{} ({}) which started at {}",
+                                index,
+                                entry.start,
+                                start
+                            );
+                            if !apply_entry(&sender, entry) {
+                                break;
+                            }
+                            let state = ScheduleStateJson { entry: index, start };
+                            if let Err(e) = state.save(&state_path) {
+                                log::error!("schedule_worker failed to save {}: {}", state_path.display(), e);
+                            }
+                        } else if starting {
+                            // the profile it loaded was saved (and may have been changed since),
+                            // but its changes were only applied on top of the settings
+                            log::debug!("Schedule entry #{} is already in effect", index);
+                            if let Some(changes) = &entry.settings {
+                                if sender.send(ApiMessage::ApplyChanges(changes.clone())).is_err() {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    last_applied = active;
+                }
+                Err(JsonError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                    last_applied = None;
+                }
+                Err(e) => {
+                    let e = e.to_string();
+                    if last_error.as_ref() != Some(&e) {
+                        log::error!("schedule_worker failed to open {}: {}", path.display(), e);
+                        last_error = Some(e);
+                    }
+                }
+            }
+            starting = false;
+            match wake.recv_timeout(PERIOD) {
+                Ok(()) => log::debug!("schedule_worker woken up early"),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => thread::sleep(PERIOD),
+            }
+        }
+        log::info!("schedule_worker stopping, api_worker is gone");
+    })
+}
+
+/// Send the messages for a schedule entry, returning false once api_worker is gone
+fn apply_entry(sender: &Sender<ApiMessage>, entry: &ScheduleEntryJson) -> bool {
+    let sent = match &entry.profile {
+        Some(ScheduleProfileJson::Main) => sender.send(ApiMessage::LoadMainSettings).is_ok(),
+        Some(ScheduleProfileJson::Game { id, name }) => {
+            let name = if name.is_empty() {
+                id.to_string()
+            } else {
+                name.clone()
+            };
+            sender.send(ApiMessage::LoadSettings(*id, name)).is_ok()
+        }
+        None => true,
+    };
+    match &entry.settings {
+        Some(changes) if sent => sender.send(ApiMessage::ApplyChanges(changes.clone())).is_ok(),
+        _ => sent,
+    }
+}
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// The entry which started most recently before `now`, with its start time.
+/// When entries start at the same time, the later one in the list wins.
+fn active_entry(entries: &[ScheduleEntryJson], now: i64) -> Option<(usize, i64)> {
+    let mut active: Option<(usize, i64)> = None;
+    for (i, entry) in entries.iter().enumerate() {
+        let minutes = if let Some(x) = entry.start_minutes() {
+            x
+        } else {
+            log::debug!("Ignoring schedule entry with invalid start `{}`", entry.start);
+            continue;
+        };
+        // a week back always includes every weekday
+        for days_back in 0..=7 {
+            if let Some((start, weekday)) = local_time_on(now, days_back, minutes) {
+                if start <= now && entry.applies_on(weekday) {
+                    if active.map(|(_, x)| start >= x).unwrap_or(true) {
+                        active = Some((i, start));
+                    }
+                    break;
+                }
+            }
+        }
+    }
+    active
+}
+
+/// Timestamp and weekday (0 = Sunday) of `minutes` past local midnight, `days_back` days before `now`.
+/// This respects the system time zone and daylight saving time.
+fn local_time_on(now: i64, days_back: u64, minutes: u32) -> Option<(i64, u8)> {
+    let date = Local
+        .timestamp_opt(now, 0)
+        .single()?
+        .date_naive()
+        .checked_sub_days(Days::new(days_back))?;
+    let time = date.and_hms_opt(minutes / 60, minutes % 60, 0)?;
+    // a time which is skipped when the clocks go forward starts an hour later instead
+    let start = Local
+        .from_local_datetime(&time)
+        .earliest()
+        .or_else(|| Local.from_local_datetime(&(time + chrono::Duration::hours(1))).earliest())?;
+    Some((start.timestamp(), date.weekday().num_days_from_sunday() as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(start: &str, days: Vec<u8>) -> ScheduleEntryJson {
+        ScheduleEntryJson {
+            start: start.to_owned(),
+            days,
+            profile: None,
+            settings: None,
+        }
+    }
+
+    #[test]
+    fn active_entry_test() {
+        let now = now();
+        let entries = vec![entry("00:00", vec![]), entry("not a time", vec![])];
+        let (index, start) = active_entry(&entries, now).unwrap();
+        assert_eq!(index, 0);
+        assert!(start <= now && start > now - 25 * 60 * 60);
+        // an entry for a single weekday was at most a week ago
+        let entries = vec![entry("12:00", vec![3])];
+        let (_, start) = active_entry(&entries, now).unwrap();
+        assert!(start <= now && start > now - 8 * 24 * 60 * 60);
+        assert!(active_entry(&[], now).is_none());
+    }
+}
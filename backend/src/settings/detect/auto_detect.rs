@@ -4,7 +4,7 @@ use regex::RegexBuilder;
 
 use limits_core::json::{BatteryLimit, CpuLimit, GpuLimit, Limits};
 
-use crate::persist::{DriverJson, SettingsJson};
+use crate::persist::{DriverJson, FingerprintJson, SettingsJson};
 use crate::settings::{Driver, General, TBattery, TCpus, TGeneral, TGpu};
 
 fn get_limits() -> limits_core::json::Base {
@@ -45,6 +45,45 @@ pub fn auto_detect_provider() -> DriverJson {
     provider
 }
 
+fn read_cpu_info() -> String {
+    usdpl_back::api::files::read_single("/proc/cpuinfo").unwrap_or_default()
+}
+
+fn read_dmi_info() -> String {
+    std::process::Command::new("dmidecode")
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).into_owned())
+        .unwrap_or_default()
+}
+
+/// Value of `field` in the first `section` of dmidecode output
+fn dmi_field(dmi_info: &str, section: &str, field: &str) -> Option<String> {
+    let prefix = format!("{}:", field);
+    dmi_info
+        .lines()
+        .skip_while(|line| line.trim() != section)
+        .skip(1)
+        .take_while(|line| !line.trim().is_empty())
+        .find_map(|line| line.trim().strip_prefix(&prefix))
+        .map(|value| value.trim().to_owned())
+}
+
+/// Identify this device from the same information used to detect it
+pub fn device_fingerprint() -> FingerprintJson {
+    let cpu_info = read_cpu_info();
+    let dmi_info = read_dmi_info();
+    FingerprintJson {
+        cpu_model: cpu_info
+            .lines()
+            .find_map(|line| line.strip_prefix("model name"))
+            .and_then(|line| line.split_once(':'))
+            .map(|(_, model)| model.trim().to_owned()),
+        manufacturer: dmi_field(&dmi_info, "System Information", "Manufacturer"),
+        product_name: dmi_field(&dmi_info, "System Information", "Product Name"),
+        board_name: dmi_field(&dmi_info, "Base Board Information", "Product Name"),
+    }
+}
+
 /// Device detection logic
 pub fn auto_detect0(
     settings_opt: Option<SettingsJson>,
@@ -53,15 +92,12 @@ pub fn auto_detect0(
 ) -> Driver {
     let mut builder = DriverBuilder::new(json_path, name);
 
-    let cpu_info: String = usdpl_back::api::files::read_single("/proc/cpuinfo").unwrap_or_default();
+    let cpu_info = read_cpu_info();
     log::debug!("Read from /proc/cpuinfo:\n{}", cpu_info);
     let os_info: String =
         usdpl_back::api::files::read_single("/etc/os-release").unwrap_or_default();
     log::debug!("Read from /etc/os-release:\n{}", os_info);
-    let dmi_info: String = std::process::Command::new("dmidecode")
-        .output()
-        .map(|out| String::from_utf8_lossy(&out.stdout).into_owned())
-        .unwrap_or_default();
+    let dmi_info = read_dmi_info();
     log::debug!("Read dmidecode:\n{}", dmi_info);
 
     let limits = get_limits();
@@ -112,6 +148,7 @@ pub fn auto_detect0(
             if let Some(settings) = &settings_opt {
                 *builder.general.persistent() = true;
                 builder.general.name(settings.name.clone());
+                builder.general.parent(settings.parent.clone());
                 for limit in conf.limits {
                     match limit {
                         Limits::Cpu(cpus) => {
@@ -210,7 +247,7 @@ pub fn auto_detect0(
                                     ),
                                 ),
                                 BatteryLimit::Unknown => {
-                                    Box::new(crate::settings::unknown::Battery)
+                                    Box::new(crate::settings::unknown::Battery::default())
                                 }
                             };
                             builder.battery = Some(driver);
@@ -274,7 +311,7 @@ pub fn auto_detect0(
                                     Box::new(crate::settings::generic::Battery::from_limits(x))
                                 }
                                 BatteryLimit::Unknown => {
-                                    Box::new(crate::settings::unknown::Battery)
+                                    Box::new(crate::settings::unknown::Battery::default())
                                 }
                             };
                             builder.battery = Some(driver);
@@ -304,6 +341,7 @@ impl DriverBuilder {
                 name: profile_name,
                 driver: DriverJson::AutoDetect,
                 events: Default::default(),
+                parent: None,
             }),
             cpus: None,
             gpu: None,
@@ -326,7 +364,7 @@ impl DriverBuilder {
                 .unwrap_or_else(|| Box::new(crate::settings::unknown::Gpu::system_default())),
             battery: self
                 .battery
-                .unwrap_or_else(|| Box::new(crate::settings::unknown::Battery)),
+                .unwrap_or_else(|| Box::new(crate::settings::unknown::Battery::default())),
         }
     }
 }
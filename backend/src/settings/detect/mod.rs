@@ -2,5 +2,5 @@ mod auto_detect;
 pub mod limits_worker;
 mod utility;
 
-pub use auto_detect::{auto_detect0, auto_detect_provider};
+pub use auto_detect::{auto_detect0, auto_detect_provider, device_fingerprint};
 pub use utility::get_dev_messages;
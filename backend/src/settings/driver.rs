@@ -1,5 +1,5 @@
 use super::{auto_detect0, General, SettingError, TBattery, TCpus, TGeneral, TGpu};
-use crate::persist::{DriverJson, SettingsJson};
+use crate::persist::{DriverJson, SettingsJson, LATEST_VERSION};
 
 pub struct Driver {
     pub general: Box<dyn TGeneral>,
@@ -14,7 +14,7 @@ impl Driver {
         json_path: std::path::PathBuf,
     ) -> Result<Self, SettingError> {
         Ok(match settings.version {
-            0 => Self::version0(settings, json_path)?,
+            0..=LATEST_VERSION => Self::version0(settings, json_path)?,
             _ => Self {
                 general: Box::new(General {
                     persistent: settings.persistent,
@@ -22,6 +22,7 @@ impl Driver {
                     name: settings.name,
                     driver: DriverJson::SteamDeck,
                     events: settings.events.unwrap_or_default(),
+                    parent: settings.parent,
                 }),
                 cpus: Box::new(super::steam_deck::Cpus::from_json(
                     settings.cpus,
@@ -53,6 +54,7 @@ impl Driver {
                         name: settings.name,
                         driver: DriverJson::SteamDeck,
                         events: settings.events.unwrap_or_default(),
+                        parent: settings.parent,
                     }),
                     cpus: Box::new(super::steam_deck::Cpus::from_json(
                         settings.cpus,
@@ -75,6 +77,7 @@ impl Driver {
                         name: settings.name,
                         driver: DriverJson::SteamDeckAdvance,
                         events: settings.events.unwrap_or_default(),
+                        parent: settings.parent,
                     }),
                     cpus: Box::new(super::steam_deck::Cpus::from_json(
                         settings.cpus,
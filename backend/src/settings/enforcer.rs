@@ -0,0 +1,163 @@
+//! Opt-in enforcement of settings which something else (e.g. Steam) keeps changing.
+//! The hardware is read back periodically, and only the values which drifted are applied again.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::api::{ActualValue, CpusActual, GpuActual};
+
+use super::{SettingError, Settings};
+
+/// Re-applies allowed per value within `RATE_LIMIT_PERIOD`, so fighting over a value doesn't become a busy loop
+const RATE_LIMIT_COUNT: usize = 3;
+const RATE_LIMIT_PERIOD: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default)]
+pub struct Enforcer {
+    /// When each value was last re-applied, within `RATE_LIMIT_PERIOD`
+    reapplied: HashMap<String, Vec<Instant>>,
+    /// Values which hit the rate limit, so that it's only logged once
+    limited: HashSet<String>,
+}
+
+/// A value which can drift, for logging
+struct Field<'a> {
+    name: String,
+    drift: &'a mut bool,
+    desired: String,
+    actual: String,
+}
+
+impl<'a> Field<'a> {
+    fn new<T: std::fmt::Display>(name: String, value: &'a mut ActualValue<T>) -> Self {
+        let show = |x: &Option<T>| x.as_ref().map(|x| x.to_string()).unwrap_or_else(|| "?".to_owned());
+        Self {
+            desired: show(&value.desired),
+            actual: show(&value.actual),
+            name,
+            drift: &mut value.drift,
+        }
+    }
+}
+
+fn fields<'a>(gpu: &'a mut GpuActual, cpus: &'a mut CpusActual) -> Vec<Field<'a>> {
+    let mut fields = vec![
+        Field::new("gpu.fast_ppt".to_owned(), &mut gpu.fast_ppt),
+        Field::new("gpu.slow_ppt".to_owned(), &mut gpu.slow_ppt),
+        Field::new("gpu.clock_min".to_owned(), &mut gpu.clock_min),
+        Field::new("gpu.clock_max".to_owned(), &mut gpu.clock_max),
+        Field::new("gpu.performance_level".to_owned(), &mut gpu.performance_level),
+        Field::new("cpus.smt".to_owned(), &mut cpus.smt),
+    ];
+    for (i, cpu) in cpus.cpus.iter_mut().enumerate() {
+        fields.push(Field::new(format!("cpu{}.online", i), &mut cpu.online));
+        fields.push(Field::new(format!("cpu{}.governor", i), &mut cpu.governor));
+        fields.push(Field::new(format!("cpu{}.clock_min", i), &mut cpu.clock_min));
+        fields.push(Field::new(format!("cpu{}.clock_max", i), &mut cpu.clock_max));
+    }
+    fields
+}
+
+impl Enforcer {
+    /// Whether `field` may be re-applied now, counting it as re-applied if so
+    fn allow(&mut self, field: &str, now: Instant) -> bool {
+        let times = self.reapplied.entry(field.to_owned()).or_default();
+        times.retain(|t| now.duration_since(*t) < RATE_LIMIT_PERIOD);
+        if times.len() < RATE_LIMIT_COUNT {
+            times.push(now);
+            self.limited.remove(field);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Re-apply the GPU and CPU values which no longer match the settings
+    pub fn enforce(&mut self, settings: &mut Settings) -> Result<(), Vec<SettingError>> {
+        let mut gpu = settings.gpu.actual();
+        let mut cpus = settings.cpus.actual();
+        let now = Instant::now();
+        let mut reapplied = Vec::new();
+        for field in fields(&mut gpu, &mut cpus) {
+            if !*field.drift {
+                continue;
+            }
+            if self.allow(&field.name, now) {
+                log::info!(
+                    "Enforcing {}: hardware has {} instead of {}, re-applying",
+                    field.name,
+                    field.actual,
+                    field.desired
+                );
+                reapplied.push((field.name, field.desired));
+            } else {
+                *field.drift = false;
+                if self.limited.insert(field.name.clone()) {
+                    log::warn!(
+                        "Enforcing {}: something else won, it keeps changing it to {} instead of {}; not re-applying for up to {}s",
+                        field.name,
+                        field.actual,
+                        field.desired,
+                        RATE_LIMIT_PERIOD.as_secs()
+                    );
+                }
+            }
+        }
+        if reapplied.is_empty() {
+            return Ok(());
+        }
+
+        let mut errors = Vec::new();
+        settings
+            .gpu
+            .reapply(&gpu)
+            .unwrap_or_else(|mut e| errors.append(&mut e));
+        settings
+            .cpus
+            .reapply(&cpus)
+            .unwrap_or_else(|mut e| errors.append(&mut e));
+
+        // read back again to tell whether the new values stuck
+        let mut gpu = settings.gpu.actual();
+        let mut cpus = settings.cpus.actual();
+        let after: HashMap<String, Field> = fields(&mut gpu, &mut cpus)
+            .into_iter()
+            .map(|f| (f.name.clone(), f))
+            .collect();
+        for (name, desired) in reapplied {
+            match after.get(&name) {
+                Some(field) if *field.drift => log::warn!(
+                    "Enforcing {}: something else won, hardware still has {} instead of {}",
+                    name,
+                    field.actual,
+                    desired
+                ),
+                _ => log::info!("Enforcing {}: PowerTools won, hardware is back to {}", name, desired),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limit_test() {
+        let mut enforcer = Enforcer::default();
+        let start = Instant::now();
+        for i in 0..RATE_LIMIT_COUNT as u64 {
+            assert!(enforcer.allow("gpu.fast_ppt", start + Duration::from_secs(i)));
+        }
+        assert!(!enforcer.allow("gpu.fast_ppt", start + Duration::from_secs(10)));
+        // other values have their own limit
+        assert!(enforcer.allow("gpu.slow_ppt", start + Duration::from_secs(10)));
+        assert!(enforcer.allow("gpu.fast_ppt", start + RATE_LIMIT_PERIOD));
+    }
+}
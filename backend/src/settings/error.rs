@@ -1,13 +1,118 @@
+use serde::{Deserialize, Serialize};
+
 use super::SettingVariant;
 
+/// What went wrong, for the front-end to pick a (localized) message
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingErrorKind {
+    /// Reading or writing a file (usually a sysfs attribute) failed
+    Io,
+    /// A file (usually a sysfs attribute) can't be accessed, e.g. when not running as root
+    PermissionDenied,
+    /// A file (usually a sysfs attribute) doesn't exist
+    NotFound,
+    /// A value was read but could not be understood
+    Parse,
+    /// The setting isn't available on this device
+    Unsupported,
+    /// The value is outside of what the device accepts
+    OutOfRange,
+    /// A hardware interface other than sysfs failed (e.g. RyzenAdj or the Steam Deck's EC)
+    Backend,
+}
+
+impl SettingErrorKind {
+    fn of_io(e: &std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::PermissionDenied => Self::PermissionDenied,
+            std::io::ErrorKind::NotFound => Self::NotFound,
+            _ => Self::Io,
+        }
+    }
+
+    /// Kind of a failed file write; sysfs rejects values which the driver doesn't accept with `EINVAL`
+    pub fn of_write(e: &std::io::Error) -> Self {
+        if e.raw_os_error() == Some(libc::EINVAL) {
+            Self::OutOfRange
+        } else {
+            Self::of_io(e)
+        }
+    }
+
+    /// Kind of a failed sysfs attribute read
+    pub fn of_read<E>(e: &sysfuss::EitherErr2<std::io::Error, E>) -> Self {
+        match e {
+            sysfuss::EitherErr2::First(e) => Self::of_io(e),
+            sysfuss::EitherErr2::Second(_) => Self::Parse,
+        }
+    }
+
+    /// Kind of a failed file read
+    pub fn of_file_read<E>(e: &usdpl_back::api::files::ReadError<E>) -> Self {
+        match e {
+            usdpl_back::api::files::ReadError::Io(e) => Self::of_io(e),
+            usdpl_back::api::files::ReadError::Parse(_) => Self::Parse,
+        }
+    }
+
+    /// Kind of a failed settings file load
+    pub fn of_json(e: &crate::persist::JsonError) -> Self {
+        match e {
+            crate::persist::JsonError::Io(e) => Self::of_io(e),
+            crate::persist::JsonError::Parent { .. } => Self::Io,
+            crate::persist::JsonError::Serde(_) | crate::persist::JsonError::Migration { .. } => Self::Parse,
+            crate::persist::JsonError::Version { .. } => Self::Unsupported,
+        }
+    }
+}
+
+impl std::fmt::Display for SettingErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Io => write!(f, "IO"),
+            Self::PermissionDenied => write!(f, "permission denied"),
+            Self::NotFound => write!(f, "not found"),
+            Self::Parse => write!(f, "parse"),
+            Self::Unsupported => write!(f, "unsupported"),
+            Self::OutOfRange => write!(f, "out of range"),
+            Self::Backend => write!(f, "hardware backend"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SettingError {
     pub msg: String,
     pub setting: SettingVariant,
+    pub kind: SettingErrorKind,
+    /// The sysfs path (or attribute) or parameter which the error is about
+    pub target: Option<String>,
 }
 
 impl std::fmt::Display for SettingError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{} setting error: {}", self.setting, self.msg)
+        write!(f, "{} setting {} error: {}", self.setting, self.kind, self.msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn setting_error_kind_test() {
+        let einval = std::io::Error::from_raw_os_error(libc::EINVAL);
+        assert_eq!(SettingErrorKind::of_write(&einval), SettingErrorKind::OutOfRange);
+        let eacces = std::io::Error::from_raw_os_error(libc::EACCES);
+        assert_eq!(SettingErrorKind::of_write(&eacces), SettingErrorKind::PermissionDenied);
+        let enoent = std::io::Error::from_raw_os_error(libc::ENOENT);
+        assert_eq!(SettingErrorKind::of_write(&enoent), SettingErrorKind::NotFound);
+        let ebusy = std::io::Error::from_raw_os_error(libc::EBUSY);
+        assert_eq!(SettingErrorKind::of_write(&ebusy), SettingErrorKind::Io);
+        assert_eq!(
+            serde_json::to_string(&SettingErrorKind::OutOfRange).unwrap(),
+            "\"out_of_range\""
+        );
     }
 }
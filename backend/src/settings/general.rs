@@ -2,14 +2,12 @@ use std::path::PathBuf;
 //use std::sync::{Arc, Mutex};
 
 //use super::{Battery, Cpus, Gpu};
-use super::{OnResume, OnSet, SettingError};
+use super::{OnResume, OnSet, SettingError, SettingErrorKind};
 use super::{TBattery, TCpus, TGeneral, TGpu};
-use crate::persist::SettingsJson;
+use crate::persist::{SettingsJson, LATEST_VERSION};
 //use crate::utility::unwrap_lock;
 
-const LATEST_VERSION: u64 = 0;
-
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub enum SettingVariant {
     Battery,
     Cpu,
@@ -35,42 +33,19 @@ pub struct General {
     pub name: String,
     pub driver: crate::persist::DriverJson,
     pub events: crate::persist::OnEventJson,
+    /// Filename of the profile these settings inherit from
+    pub parent: Option<String>,
 }
 
+// event commands need the context of all settings, so they are run by Settings instead
 impl OnSet for General {
     fn on_set(&mut self) -> Result<(), Vec<SettingError>> {
-        if let Some(event) = &self.events.on_set {
-            if !event.is_empty() {
-                std::process::Command::new("/bin/bash")
-                    .args(&["-c", event])
-                    .spawn()
-                    .map_err(|e| {
-                        vec![SettingError {
-                            msg: format!("on_set event command error: {}", e),
-                            setting: SettingVariant::General,
-                        }]
-                    })?;
-            }
-        }
         Ok(())
     }
 }
 
 impl OnResume for General {
     fn on_resume(&self) -> Result<(), Vec<SettingError>> {
-        if let Some(event) = &self.events.on_resume {
-            if !event.is_empty() {
-                std::process::Command::new("/bin/bash")
-                    .args(&["-c", event])
-                    .spawn()
-                    .map_err(|e| {
-                        vec![SettingError {
-                            msg: format!("on_resume event command error: {}", e),
-                            setting: SettingVariant::General,
-                        }]
-                    })?;
-            }
-        }
         Ok(())
     }
 }
@@ -106,6 +81,14 @@ impl TGeneral for General {
         self.name = name;
     }
 
+    fn get_parent(&self) -> Option<&'_ str> {
+        self.parent.as_deref()
+    }
+
+    fn parent(&mut self, parent: Option<String>) {
+        self.parent = parent;
+    }
+
     fn provider(&self) -> crate::persist::DriverJson {
         self.driver.clone()
     }
@@ -121,6 +104,26 @@ pub struct Settings {
     pub cpus: Box<dyn TCpus>,
     pub gpu: Box<dyn TGpu>,
     pub battery: Box<dyn TBattery>,
+    /// Settings which were adjusted to fit within the drivers' limits when they were loaded
+    pub limit_violations: Vec<crate::api::LimitViolation>,
+    /// Settings as they were last applied, to tell event commands what changed
+    applied: Option<SettingsJson>,
+    /// Partial settings changes to apply on power events
+    pub rules: Vec<crate::persist::RuleJson>,
+    /// Periodically re-apply settings which something else changed
+    pub enforce: bool,
+    triggers: super::rules::TriggerState,
+    /// Changes of the schedule entry which is in effect, until another profile is loaded.
+    /// Like rules, they are only applied on top of the settings.
+    scheduled: Option<crate::persist::PartialSettingsJson>,
+    /// Power rules which have fired, by index, from the first to fire to the last.
+    /// Like temperature rules, their changes are only applied on top of the settings.
+    power: Vec<usize>,
+    /// Temperature rules which are active, by index.
+    /// Their changes are only applied on top of the settings, so they are never saved.
+    thermal: std::collections::BTreeSet<usize>,
+    /// Thermal safety layer, which wraps the GPU and CPUs
+    governor: super::governor::ThermalGovernor,
 }
 
 impl OnSet for Settings {
@@ -132,18 +135,17 @@ impl OnSet for Settings {
             .on_set()
             .unwrap_or_else(|mut e| errors.append(&mut e));
         log::debug!("Set general");
-        self.battery
-            .on_set()
+        self.set_drivers()
             .unwrap_or_else(|mut e| errors.append(&mut e));
-        log::debug!("Set battery");
-        self.cpus
-            .on_set()
-            .unwrap_or_else(|mut e| errors.append(&mut e));
-        log::debug!("Set CPUs");
-        self.gpu
-            .on_set()
-            .unwrap_or_else(|mut e| errors.append(&mut e));
-        log::debug!("Set GPU");
+
+        let current = self.json();
+        let changed = self
+            .applied
+            .as_ref()
+            .map(|prev| prev.changed_fields(&current))
+            .unwrap_or_default();
+        self.applied = Some(current);
+        self.run_event("on_set", &changed);
 
         if errors.is_empty() {
             Ok(())
@@ -157,6 +159,8 @@ impl Settings {
     #[inline]
     pub fn from_json(other: SettingsJson, json_path: PathBuf) -> Self {
         let name_bup = other.name.clone();
+        let rules = other.rules.clone();
+        let enforce = other.enforce;
         match super::Driver::init(other, json_path.clone()) {
             Ok(x) => {
                 log::info!(
@@ -166,12 +170,24 @@ impl Settings {
                     x.gpu.provider(),
                     x.battery.provider()
                 );
-                Self {
+                let governor = super::governor::ThermalGovernor::load();
+                let mut settings = Self {
                     general: x.general,
-                    cpus: x.cpus,
-                    gpu: x.gpu,
+                    cpus: governor.wrap_cpus(x.cpus),
+                    gpu: governor.wrap_gpu(x.gpu),
                     battery: x.battery,
-                }
+                    limit_violations: Vec::new(),
+                    applied: None,
+                    rules,
+                    enforce,
+                    triggers: Default::default(),
+                    scheduled: None,
+                    power: Vec::new(),
+                    thermal: Default::default(),
+                    governor,
+                };
+                settings.validate();
+                settings
             }
             Err(e) => {
                 log::error!("Driver init error: {}", e);
@@ -182,20 +198,83 @@ impl Settings {
 
     pub fn system_default(json_path: PathBuf, name: String) -> Self {
         let driver = super::Driver::system_default(json_path, name);
+        let governor = super::governor::ThermalGovernor::load();
         Self {
             general: driver.general,
-            cpus: driver.cpus,
-            gpu: driver.gpu,
+            cpus: governor.wrap_cpus(driver.cpus),
+            gpu: governor.wrap_gpu(driver.gpu),
             battery: driver.battery,
+            limit_violations: Vec::new(),
+            applied: None,
+            rules: Vec::new(),
+            enforce: false,
+            triggers: Default::default(),
+            scheduled: None,
+            power: Vec::new(),
+            thermal: Default::default(),
+            governor,
         }
     }
 
     pub fn load_system_default(&mut self, name: String) {
         let driver = super::Driver::system_default(self.general.get_path().to_owned(), name);
-        self.cpus = driver.cpus;
-        self.gpu = driver.gpu;
+        self.cpus = self.governor.wrap_cpus(driver.cpus);
+        self.gpu = self.governor.wrap_gpu(driver.gpu);
         self.battery = driver.battery;
         self.general = driver.general;
+        self.rules.clear();
+        self.enforce = false;
+        self.limit_violations.clear();
+    }
+
+    /// Undo everything which has been applied, for when PowerTools exits.
+    /// The drivers reset what they changed first, then `original` is written back on top.
+    pub fn restore_system(&mut self, original: &super::OriginalState) -> Result<(), Vec<SettingError>> {
+        let mut errors = Vec::new();
+        self.governor.release();
+        *self.cpus.smt() = true;
+        for cpu in self.cpus.cpus() {
+            *cpu.online() = true;
+            cpu.clock_limits(None);
+        }
+        self.gpu.ppt(None, None);
+        self.gpu.clock_limits(None);
+        *self.gpu.slow_memory() = false;
+        self.battery.charge_rate(None);
+        self.battery.charge_mode(None);
+        self.battery.charge_limit(None);
+        self.cpus
+            .on_set()
+            .unwrap_or_else(|mut e| errors.append(&mut e));
+        self.gpu
+            .on_set()
+            .unwrap_or_else(|mut e| errors.append(&mut e));
+        self.battery
+            .on_set()
+            .unwrap_or_else(|mut e| errors.append(&mut e));
+        original
+            .restore()
+            .unwrap_or_else(|mut e| errors.append(&mut e));
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Check the settings against the drivers' limits, adjusting anything outside of them
+    fn validate(&mut self) {
+        self.limit_violations = super::check_limits(self, true);
+        for violation in &self.limit_violations {
+            log::warn!(
+                "Setting {} = {} is outside of limits ({}), {:?} to {}",
+                violation.setting,
+                violation.value,
+                violation.allowed,
+                violation.action,
+                violation.new_value.as_deref().unwrap_or("nothing")
+            );
+        }
     }
 
     pub fn load_file(
@@ -209,6 +288,8 @@ impl Settings {
             let settings_json = SettingsJson::open(&json_path).map_err(|e| SettingError {
                 msg: e.to_string(),
                 setting: SettingVariant::General,
+                kind: SettingErrorKind::of_json(&e),
+                target: Some(json_path.display().to_string()),
             })?;
             if !settings_json.persistent {
                 log::warn!(
@@ -219,13 +300,18 @@ impl Settings {
                 *self.general.persistent() = false;
                 self.general.name(name);
             } else {
+                let rules = settings_json.rules.clone();
+                let enforce = settings_json.enforce;
                 match super::Driver::init(settings_json, json_path.clone()) {
                     Ok(x) => {
                         log::info!("Loaded settings with drivers general:{:?},cpus:{:?},gpu:{:?},battery:{:?}", x.general.provider(), x.cpus.provider(), x.gpu.provider(), x.battery.provider());
                         self.general = x.general;
-                        self.cpus = x.cpus;
-                        self.gpu = x.gpu;
+                        self.cpus = self.governor.wrap_cpus(x.cpus);
+                        self.gpu = self.governor.wrap_gpu(x.gpu);
                         self.battery = x.battery;
+                        self.rules = rules;
+                        self.enforce = enforce;
+                        self.validate();
                     }
                     Err(e) => {
                         log::error!("Driver init error: {}", e);
@@ -245,17 +331,12 @@ impl Settings {
             *self.general.persistent() = false;
         }
         self.general.path(filename);
-        if let Some(event) = &self.general.on_event().on_load {
-            if !event.is_empty() {
-                std::process::Command::new("/bin/bash")
-                    .args(&["-c", event])
-                    .spawn()
-                    .map_err(|e| SettingError {
-                        msg: format!("on_save event command error: {}", e),
-                        setting: SettingVariant::General,
-                    })?;
-            }
-        }
+        self.applied = None;
+        self.triggers.reset();
+        self.scheduled = None;
+        self.power.clear();
+        self.thermal.clear();
+        self.run_event("on_load", &[]);
         Ok(*self.general.persistent())
     }
 
@@ -290,6 +371,312 @@ impl Settings {
         Ok(*self.general.persistent())
     }*/
 
+    /// Apply a partial settings change, keeping it within the drivers' limits.
+    /// It takes effect on the next `on_set()`.
+    pub fn apply_changes(&mut self, changes: &crate::persist::PartialSettingsJson) {
+        super::rules::apply_changes(self, changes);
+        for violation in super::check_limits(self, true) {
+            log::warn!(
+                "Change to {} = {} is outside of limits ({}), {:?} to {}",
+                violation.setting,
+                violation.value,
+                violation.allowed,
+                violation.action,
+                violation.new_value.as_deref().unwrap_or("nothing")
+            );
+        }
+    }
+
+    /// State of the thermal governor, for display
+    pub fn thermal_status(&self) -> crate::api::ThermalStatus {
+        // temperatures are only checked periodically while something needs them
+        let readings = if self.watches_temperatures() {
+            self.governor.readings
+        } else {
+            super::governor::ThermalReadings {
+                apu: super::thermal::apu_temperature(&super::thermal::read_temperatures()),
+                battery: super::thermal::read_battery_temperature(),
+            }
+        };
+        crate::api::ThermalStatus {
+            config: self.governor.config.clone(),
+            level: self.governor.level(),
+            max_level: super::governor::MAX_LEVEL,
+            throttling: self.governor.level() != 0,
+            apu_temperature: readings.apu,
+            battery_temperature: readings.battery,
+        }
+    }
+
+    /// Whether temperatures need to be checked, for temperature rules or the thermal governor
+    pub fn watches_temperatures(&self) -> bool {
+        self.governor.config.enabled
+            || self.governor.level() != 0
+            || !self.thermal.is_empty()
+            || self.rules.iter().any(|rule| {
+                matches!(
+                    super::rules::Trigger::parse(&rule.trigger),
+                    Some(super::rules::Trigger::TemperatureAbove(_))
+                )
+            })
+    }
+
+    /// Change the thermal governor configuration, which takes effect on the next temperature check
+    pub fn set_thermal_config(&mut self, config: crate::persist::ThermalGovernorJson) {
+        self.governor.config = config;
+    }
+
+    /// Take the GPU, CPU and battery values of `json` into the current drivers
+    fn load_driver_json(&mut self, json: &SettingsJson) {
+        self.gpu.load_json(json.gpu.clone());
+        if self.cpus.limits().smt_capable {
+            *self.cpus.smt() = super::util::guess_smt(&json.cpus);
+        }
+        for (cpu, cpu_json) in self.cpus.cpus().into_iter().zip(json.cpus.iter()) {
+            *cpu.online() = cpu_json.online;
+            cpu.governor(cpu_json.governor.clone());
+            cpu.clock_limits(
+                cpu_json
+                    .clock_limits
+                    .clone()
+                    .map(|x| super::min_max_from_json(x, LATEST_VERSION)),
+            );
+        }
+        self.battery.charge_rate(json.battery.charge_rate);
+        self.battery.charge_mode(json.battery.charge_mode.clone());
+    }
+
+    /// Fit the GPU, CPU and battery values of `json` (e.g. from another device) within the limits of the current drivers.
+    /// Nothing is applied, and the current settings are left as they were.
+    pub fn clamp_json(&mut self, mut json: SettingsJson) -> (SettingsJson, Vec<crate::api::LimitViolation>) {
+        let current = self.json();
+        self.load_driver_json(&json);
+        let violations = super::check_limits(self, true);
+        let clamped = self.json();
+        self.load_driver_json(&current);
+        json.gpu = clamped.gpu;
+        json.cpus = clamped.cpus;
+        json.battery.charge_rate = clamped.battery.charge_rate;
+        json.battery.charge_mode = clamped.battery.charge_mode;
+        (json, violations)
+    }
+
+    /// Take the values of `json` (e.g. from history) into the current drivers, without re-initializing them.
+    /// `json` must be from the same profile (and device), since values aren't checked against limits.
+    pub fn load_json(&mut self, json: SettingsJson) {
+        // battery events and event commands can't be set through the drivers, so those are re-initialized instead
+        let battery_events = |battery: &crate::persist::BatteryJson| serde_json::to_value(&battery.events).ok();
+        let hooks = |events: Option<&crate::persist::OnEventJson>| serde_json::to_value(events).ok();
+        if battery_events(&self.battery.json()) != battery_events(&json.battery)
+            || hooks(Some(self.general.on_event())) != hooks(json.events.as_ref())
+        {
+            match super::Driver::init(json.clone(), self.general.get_path().to_owned()) {
+                Ok(driver) => {
+                    self.general = driver.general;
+                    self.battery = driver.battery;
+                }
+                Err(e) => log::error!("Failed to restore battery events and event commands: {}", e),
+            }
+        }
+        self.load_driver_json(&json);
+        self.general.name(json.name);
+        *self.general.persistent() = json.persistent;
+        self.general.parent(json.parent);
+        if serde_json::to_value(&self.rules).ok() != serde_json::to_value(&json.rules).ok() {
+            self.set_rules(json.rules);
+        }
+        self.enforce = json.enforce;
+    }
+
+    /// Replace the power event rules, re-arming all triggers
+    pub fn set_rules(&mut self, rules: Vec<crate::persist::RuleJson>) {
+        self.power.clear();
+        self.thermal.clear();
+        self.rules = rules;
+        self.triggers.reset();
+    }
+
+    /// Apply temperature rules which have become too hot, and undo those which have cooled down.
+    /// The thermal governor is updated too.
+    pub fn check_temperatures(&mut self) -> Result<(), Vec<SettingError>> {
+        let readings = super::thermal::read_temperatures();
+        let mut changed = self.governor.update(super::governor::ThermalReadings {
+            apu: super::thermal::apu_temperature(&readings),
+            battery: super::thermal::read_battery_temperature(),
+        });
+        for (i, rule) in self.rules.iter().enumerate() {
+            let threshold = match super::rules::Trigger::parse(&rule.trigger) {
+                Some(super::rules::Trigger::TemperatureAbove(x)) => x,
+                _ => continue,
+            };
+            let temperature = if let Some(x) = super::thermal::max_temperature(&readings, rule.sensor.as_deref()) {
+                x
+            } else {
+                continue;
+            };
+            let hysteresis = rule.hysteresis.unwrap_or(super::rules::DEFAULT_HYSTERESIS);
+            if !self.thermal.contains(&i) && temperature > threshold {
+                log::info!("Rule #{} ({}) triggered at {}C", i, rule.trigger, temperature);
+                self.thermal.insert(i);
+                changed = true;
+            } else if temperature < threshold - hysteresis && self.thermal.remove(&i) {
+                log::info!("Rule #{} ({}) undone at {}C", i, rule.trigger, temperature);
+                changed = true;
+            }
+        }
+        if changed {
+            self.set_drivers()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Apply the changes of the schedule entry in effect, replacing those of the previous entry.
+    /// They are applied on top of the settings, so they are never saved.
+    pub fn schedule_changes(&mut self, changes: crate::persist::PartialSettingsJson) -> Result<(), Vec<SettingError>> {
+        self.scheduled = Some(changes);
+        self.set_drivers()
+    }
+
+    /// Apply the changes of the schedule, fired power rules and active temperature rules again,
+    /// i.e. after `on_resume()` applied the settings without them
+    pub fn reapply_rules(&mut self) -> Result<(), Vec<SettingError>> {
+        if self.scheduled.is_none() && self.power.is_empty() && self.thermal.is_empty() {
+            Ok(())
+        } else {
+            self.set_drivers()
+        }
+    }
+
+    /// Run `f` with the changes of the schedule, then fired power rules and then active temperature rules
+    /// applied on top of the settings.
+    /// The settings are restored afterwards, like the thermal governor does with its throttled values.
+    pub fn with_active_rules<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let changes: Vec<_> = self
+            .scheduled
+            .iter()
+            .cloned()
+            .chain(
+                self.power
+                    .iter()
+                    .chain(self.thermal.iter())
+                    .filter_map(|i| self.rules.get(*i))
+                    .map(|rule| rule.changes.clone()),
+            )
+            .collect();
+        let mut snapshots = Vec::with_capacity(changes.len());
+        for changes in &changes {
+            snapshots.push(super::rules::Snapshot::take(self, changes));
+            self.apply_changes(changes);
+        }
+        let result = f(self);
+        for snapshot in snapshots.into_iter().rev() {
+            snapshot.restore(self);
+        }
+        result
+    }
+
+    /// Apply the battery, CPU and GPU settings, with the schedule's changes and active rules on top
+    fn set_drivers(&mut self) -> Result<(), Vec<SettingError>> {
+        self.with_active_rules(|settings| {
+            let mut errors = Vec::new();
+            settings
+                .battery
+                .on_set()
+                .unwrap_or_else(|mut e| errors.append(&mut e));
+            log::debug!("Set battery");
+            settings
+                .cpus
+                .on_set()
+                .unwrap_or_else(|mut e| errors.append(&mut e));
+            log::debug!("Set CPUs");
+            settings
+                .gpu
+                .on_set()
+                .unwrap_or_else(|mut e| errors.append(&mut e));
+            log::debug!("Set GPU");
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(errors)
+            }
+        })
+    }
+
+    /// Run the command for `event` (e.g. "on_set") in the background, if there is one.
+    /// Context is passed to the command as `POWERTOOLS_*` environment variables.
+    pub fn run_event(&self, event: &'static str, changed: &[String]) {
+        let events = self.general.on_event();
+        let command = match event {
+            "on_save" => &events.on_save,
+            "on_load" => &events.on_load,
+            "on_set" => &events.on_set,
+            "on_resume" => &events.on_resume,
+            "on_plugged_in" => &events.on_plugged_in,
+            "on_unplugged" => &events.on_unplugged,
+            _ => {
+                log::error!("Unknown event {}", event);
+                return;
+            }
+        };
+        if let Some(command) = command {
+            self.spawn_event(event, command, self.event_env(event, changed));
+        }
+    }
+
+    fn spawn_event(&self, event: &'static str, command: &str, env: Vec<(String, String)>) {
+        if command.is_empty() {
+            return;
+        }
+        let timeout = self
+            .general
+            .on_event()
+            .timeout
+            .map(std::time::Duration::from_secs)
+            .unwrap_or(super::hooks::DEFAULT_HOOK_TIMEOUT);
+        super::hooks::spawn_hook(event, command.to_owned(), env, timeout);
+    }
+
+    fn event_env(&self, event: &str, changed: &[String]) -> Vec<(String, String)> {
+        fn opt<T: ToString>(value: Option<T>) -> String {
+            value.map(|x| x.to_string()).unwrap_or_default()
+        }
+        let provider = serde_json::to_value(self.general.provider())
+            .ok()
+            .and_then(|x| x.as_str().map(|x| x.to_owned()))
+            .unwrap_or_default();
+        let (fast_ppt, slow_ppt) = self.gpu.get_ppt();
+        let gpu_clocks = self.gpu.get_clock_limits();
+        let charge = match (self.battery.read_charge_now(), self.battery.read_charge_full()) {
+            (Some(now), Some(full)) if full > 0.0 => Some(now / full * 100.0),
+            _ => None,
+        };
+        vec![
+            ("POWERTOOLS_EVENT", event.to_owned()),
+            ("POWERTOOLS_PROFILE_NAME", self.general.get_name().to_owned()),
+            (
+                "POWERTOOLS_PROFILE_PATH",
+                crate::utility::settings_dir()
+                    .join(self.general.get_path())
+                    .display()
+                    .to_string(),
+            ),
+            ("POWERTOOLS_PROVIDER", provider),
+            ("POWERTOOLS_CHANGED", changed.join(",")),
+            ("POWERTOOLS_GPU_FAST_PPT", opt(fast_ppt)),
+            ("POWERTOOLS_GPU_SLOW_PPT", opt(slow_ppt)),
+            ("POWERTOOLS_GPU_CLOCK_MIN", opt(gpu_clocks.and_then(|x| x.min))),
+            ("POWERTOOLS_GPU_CLOCK_MAX", opt(gpu_clocks.and_then(|x| x.max))),
+            ("POWERTOOLS_CHARGE_RATE", opt(self.battery.get_charge_rate())),
+            ("POWERTOOLS_CHARGE_MODE", opt(self.battery.get_charge_mode())),
+            ("POWERTOOLS_CHARGE_LIMIT", opt(self.battery.get_charge_limit())),
+            ("POWERTOOLS_BATTERY_PERCENT", opt(charge.map(|x| format!("{:.1}", x)))),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value))
+        .collect()
+    }
+
     pub fn json(&self) -> SettingsJson {
         SettingsJson {
             version: LATEST_VERSION,
@@ -300,6 +687,9 @@ impl Settings {
             battery: self.battery.json(),
             provider: Some(self.general.provider()),
             events: Some(self.general.on_event().clone()),
+            parent: self.general.get_parent().map(|x| x.to_owned()),
+            rules: self.rules.clone(),
+            enforce: self.enforce,
         }
     }
 }
@@ -325,6 +715,7 @@ impl OnResume for Settings {
             .on_resume()
             .unwrap_or_else(|mut e| errors.append(&mut e));
         log::debug!("Resumed GPU");
+        self.run_event("on_resume", &[]);
 
         if errors.is_empty() {
             Ok(())
@@ -351,6 +742,27 @@ impl crate::settings::OnPowerEvent for Settings {
             .on_power_event(new_mode)
             .unwrap_or_else(|mut e| errors.append(&mut e));
 
+        let fired = self
+            .triggers
+            .power_event(new_mode, &self.rules, self.general.on_event());
+        for &i in &fired.rules {
+            // a rule which fires again takes precedence over the others again
+            self.power.retain(|x| *x != i);
+            self.power.push(i);
+        }
+        if !fired.rules.is_empty() {
+            // like battery events, rules take effect right away
+            self.set_drivers()
+                .unwrap_or_else(|mut e| errors.append(&mut e));
+        }
+        for fired in fired.events {
+            let mut env = self.event_env(fired.event, &[]);
+            if let Some(trigger) = fired.trigger {
+                env.push(("POWERTOOLS_TRIGGER".to_owned(), trigger));
+            }
+            self.spawn_event(fired.event, &fired.command, env);
+        }
+
         if errors.is_empty() {
             Ok(())
         } else {
@@ -374,3 +786,98 @@ impl crate::settings::OnPowerEvent for Settings {
         }
     }
 }*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thermal_rules_not_saved_test() {
+        let json: SettingsJson = serde_json::from_value(serde_json::json!({
+            "version": crate::persist::LATEST_VERSION,
+            "name": "Cool name",
+            "persistent": true,
+            "cpus": [
+                {"online": true, "clock_limits": null, "governor": "schedutil"},
+                {"online": true, "clock_limits": null, "governor": "schedutil"},
+            ],
+            "gpu": {"fast_ppt": 15000000, "slow_ppt": 15000000, "clock_limits": null, "slow_memory": false},
+            "battery": {"charge_rate": null, "charge_mode": null},
+            "provider": "steam-deck",
+            "rules": [{"trigger": "temp>85", "fast_ppt": 5, "governor": "powersave"}],
+        }))
+        .unwrap();
+        let mut settings = Settings::from_json(json, "idc".into());
+        let saved = serde_json::to_value(settings.json()).unwrap();
+        // as if check_temperatures() found it too hot
+        settings.thermal.insert(0);
+        let applied = settings.with_active_rules(|settings| settings.json());
+        assert_eq!(applied.gpu.fast_ppt, Some(5000000));
+        assert_eq!(applied.cpus[1].governor, "powersave");
+        // what gets saved (and recorded in history) while triggered is still the user's
+        assert_eq!(serde_json::to_value(settings.json()).unwrap(), saved);
+        settings.gpu.ppt(Some(10), None);
+        settings.with_active_rules(|_| ());
+        assert_eq!(settings.gpu.get_ppt(), (Some(10), None));
+    }
+
+    #[test]
+    fn power_rules_not_saved_test() {
+        let json: SettingsJson = serde_json::from_value(serde_json::json!({
+            "version": crate::persist::LATEST_VERSION,
+            "name": "Cool name",
+            "persistent": true,
+            "cpus": [
+                {"online": true, "clock_limits": null, "governor": "schedutil"},
+                {"online": true, "clock_limits": null, "governor": "schedutil"},
+            ],
+            "gpu": {"fast_ppt": 15000000, "slow_ppt": 15000000, "clock_limits": null, "slow_memory": false},
+            "battery": {"charge_rate": null, "charge_mode": null},
+            "provider": "steam-deck",
+            "rules": [{"trigger": "plug-out", "fast_ppt": 5}],
+        }))
+        .unwrap();
+        let mut settings = Settings::from_json(json, "idc".into());
+        let saved = serde_json::to_value(settings.json()).unwrap();
+        // there is no hardware to apply to here, so only the result matters
+        let _ = super::super::OnPowerEvent::on_power_event(&mut settings, super::super::PowerMode::PluggedOut);
+        assert_eq!(settings.power, vec![0]);
+        assert_eq!(serde_json::to_value(settings.json()).unwrap(), saved);
+        let applied = settings.with_active_rules(|settings| settings.json());
+        assert_eq!(applied.gpu.fast_ppt, Some(5000000));
+    }
+
+    #[test]
+    fn load_json_restores_everything_test() {
+        let json = |events: serde_json::Value, hooks: serde_json::Value| -> SettingsJson {
+            serde_json::from_value(serde_json::json!({
+                "version": crate::persist::LATEST_VERSION,
+                "name": "Cool name",
+                "persistent": true,
+                "cpus": [
+                    {"online": true, "clock_limits": null, "governor": "schedutil"},
+                    {"online": true, "clock_limits": null, "governor": "schedutil"},
+                ],
+                "gpu": {"fast_ppt": 15000000, "slow_ppt": 15000000, "clock_limits": null, "slow_memory": false},
+                "battery": {"charge_rate": null, "charge_mode": null, "events": events},
+                "provider": "steam-deck",
+                "events": hooks,
+            }))
+            .unwrap()
+        };
+        let snapshot = Settings::from_json(
+            json(
+                serde_json::json!([{"trigger": ">80", "charge_rate": 500, "charge_mode": null}]),
+                serde_json::json!({"on_save": null, "on_load": null, "on_set": "echo set", "on_resume": null}),
+            ),
+            "idc".into(),
+        )
+        .json();
+        let mut settings = Settings::from_json(json(serde_json::json!([]), serde_json::Value::Null), "idc".into());
+        settings.load_json(snapshot.clone());
+        assert_eq!(
+            serde_json::to_value(settings.json()).unwrap(),
+            serde_json::to_value(snapshot).unwrap()
+        );
+    }
+}
@@ -4,14 +4,15 @@ use limits_core::json::GenericBatteryLimit;
 use sysfuss::SysEntity;
 
 use crate::persist::BatteryJson;
-use crate::settings::TBattery;
-use crate::settings::{OnResume, OnSet, SettingError};
+use crate::settings::{PowerMode, TBattery};
+use crate::settings::{OnResume, OnSet, SettingError, SettingErrorKind};
 
 #[derive(Debug, Clone)]
 pub struct Battery {
     #[allow(dead_code)]
     limits: GenericBatteryLimit,
     sysfs: sysfuss::PowerSupplyPath,
+    state: crate::state::generic::Battery,
 }
 
 impl Into<BatteryJson> for Battery {
@@ -33,6 +34,8 @@ impl Battery {
             Err(e) => Err(SettingError {
                 msg: format!("Failed to read from `{}`: {}", path.display(), e),
                 setting: crate::settings::SettingVariant::Battery,
+                kind: SettingErrorKind::of_file_read(&e),
+                target: Some(path.display().to_string()),
             }),
             // this value is in uA, while it's set in mA
             // so convert this to mA for consistency
@@ -62,6 +65,7 @@ impl Battery {
         Self {
             limits,
             sysfs: Self::find_psu_sysfs(None::<&'static str>),
+            state: crate::state::generic::Battery::default(),
         }
     }
 
@@ -73,7 +77,8 @@ impl Battery {
         // TODO
         Self {
             limits,
-            sysfs: Self::find_psu_sysfs(other.root)
+            sysfs: Self::find_psu_sysfs(other.root),
+            state: crate::state::generic::Battery::default(),
         }
     }
 }
@@ -165,6 +170,20 @@ impl TBattery for Battery {
         None
     }
 
+    fn check_power(&mut self) -> Result<Vec<PowerMode>, Vec<SettingError>> {
+        log::debug!("Generic power vibe check");
+        let mut events = Vec::new();
+        if let (Some(full), Some(now)) = (self.read_charge_full(), self.read_charge_now()) {
+            events.push(PowerMode::BatteryCharge(now / full));
+        }
+        let root = crate::settings::util::root_or_default_sysfs(self.sysfs.root());
+        let online = crate::settings::util::read_charger_online(&root);
+        if let Some(event) = crate::settings::util::charger_event(&mut self.state.charger_state, online) {
+            events.push(event);
+        }
+        Ok(events)
+    }
+
     fn provider(&self) -> crate::persist::DriverJson {
         crate::persist::DriverJson::Generic
     }
@@ -6,7 +6,7 @@ use super::FromGenericCpuInfo;
 use crate::api::RangeLimit;
 use crate::persist::CpuJson;
 use crate::settings::{min_max_from_json, MinMax};
-use crate::settings::{OnResume, OnSet, SettingError};
+use crate::settings::{OnResume, OnSet, SettingError, SettingErrorKind};
 use crate::settings::{TCpu, TCpus};
 
 const CPU_PRESENT_PATH: &str = "/sys/devices/system/cpu/present";
@@ -29,6 +29,8 @@ impl<C: AsMut<Cpu> + AsRef<Cpu> + TCpu + OnSet> OnSet for Cpus<C> {
                     .map_err(|e| SettingError {
                         msg: format!("Failed to write `on` to `{}`: {}", CPU_SMT_PATH, e),
                         setting: crate::settings::SettingVariant::Cpu,
+                        kind: SettingErrorKind::of_write(&e),
+                        target: Some(CPU_SMT_PATH.to_owned()),
                     })
                     .unwrap_or_else(|e| errors.push(e));
             } else {
@@ -36,6 +38,8 @@ impl<C: AsMut<Cpu> + AsRef<Cpu> + TCpu + OnSet> OnSet for Cpus<C> {
                     .map_err(|e| SettingError {
                         msg: format!("Failed to write `off` to `{}`: {}", CPU_SMT_PATH, e),
                         setting: crate::settings::SettingVariant::Cpu,
+                        kind: SettingErrorKind::of_write(&e),
+                        target: Some(CPU_SMT_PATH.to_owned()),
                     })
                     .unwrap_or_else(|e| errors.push(e));
             }
@@ -189,6 +193,18 @@ impl<C: AsMut<Cpu> + AsRef<Cpu> + TCpu + OnResume + OnSet + crate::settings::OnP
         &mut self.smt
     }
 
+    fn actual(&self) -> crate::api::CpusActual {
+        crate::settings::util::cpus_actual(
+            self.smt,
+            self.smt_capable,
+            self.cpus.iter().map(|x| x.actual()).collect(),
+        )
+    }
+
+    fn reapply(&mut self, actual: &crate::api::CpusActual) -> Result<(), Vec<SettingError>> {
+        crate::settings::util::reapply_cpus(actual)
+    }
+
     fn provider(&self) -> crate::persist::DriverJson {
         crate::persist::DriverJson::Generic
     }
@@ -286,6 +302,8 @@ impl Cpu {
                 .map_err(|e| SettingError {
                     msg: format!("Failed to write to `{}`: {}", &online_path, e),
                     setting: crate::settings::SettingVariant::Cpu,
+                    kind: SettingErrorKind::of_write(&e),
+                    target: Some(online_path.clone()),
                 })
                 .unwrap_or_else(|e| errors.push(e));
         }
@@ -300,6 +318,8 @@ impl Cpu {
                         &self.governor, &governor_path, e
                     ),
                     setting: crate::settings::SettingVariant::Cpu,
+                    kind: SettingErrorKind::of_write(&e),
+                    target: Some(governor_path.clone()),
                 })
                 .unwrap_or_else(|e| errors.push(e));
         }
@@ -402,6 +422,10 @@ impl TCpu for Cpu {
     fn get_clock_limits(&self) -> Option<&MinMax<u64>> {
         self.clock_limits.as_ref()
     }
+
+    fn actual(&self) -> crate::api::CpuActual {
+        crate::settings::util::cpu_actual(self.index, self.online, &self.governor)
+    }
 }
 
 #[inline]
@@ -204,6 +204,16 @@ impl TGpu for Gpu {
         &mut self.slow_memory
     }
 
+    fn actual(&self) -> crate::api::GpuActual {
+        // nothing is applied, so there is nothing to read back
+        crate::api::GpuActual::new(self.get_ppt(), self.get_clock_limits(), (None, None), None)
+    }
+
+    fn reapply(&mut self, _actual: &crate::api::GpuActual) -> Result<(), Vec<SettingError>> {
+        // nothing is read back, so nothing can drift
+        Ok(())
+    }
+
     fn provider(&self) -> crate::persist::DriverJson {
         crate::persist::DriverJson::Generic
     }
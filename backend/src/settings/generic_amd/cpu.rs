@@ -64,6 +64,14 @@ impl TCpus for Cpus {
         self.generic.smt()
     }
 
+    fn actual(&self) -> crate::api::CpusActual {
+        self.generic.actual()
+    }
+
+    fn reapply(&mut self, actual: &crate::api::CpusActual) -> Result<(), Vec<SettingError>> {
+        self.generic.reapply(actual)
+    }
+
     fn provider(&self) -> crate::persist::DriverJson {
         crate::persist::DriverJson::GenericAMD
     }
@@ -140,4 +148,8 @@ impl TCpu for Cpu {
     fn get_clock_limits(&self) -> Option<&MinMax<u64>> {
         self.generic.get_clock_limits()
     }
+
+    fn actual(&self) -> crate::api::CpuActual {
+        self.generic.actual()
+    }
 }
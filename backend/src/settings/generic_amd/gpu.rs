@@ -5,7 +5,7 @@ use crate::persist::GpuJson;
 use crate::settings::generic::Gpu as GenericGpu;
 use crate::settings::MinMax;
 use crate::settings::TGpu;
-use crate::settings::{OnResume, OnSet, SettingError, SettingVariant};
+use crate::settings::{OnResume, OnSet, SettingError, SettingErrorKind, SettingVariant};
 
 fn ryzen_adj_or_log() -> Option<Mutex<RyzenAdj>> {
     match RyzenAdj::new() {
@@ -63,6 +63,8 @@ impl Gpu {
                 return Err(vec![SettingError {
                     msg: "RyzenAdj unavailable".to_owned(),
                     setting: SettingVariant::Gpu,
+                    kind: SettingErrorKind::Unsupported,
+                    target: None,
                 }]);
             }
         };
@@ -72,6 +74,8 @@ impl Gpu {
                 return Err(vec![SettingError {
                     msg: format!("RyzenAdj lock acquire failed: {}", e),
                     setting: SettingVariant::Gpu,
+                    kind: SettingErrorKind::Backend,
+                    target: None,
                 }]);
             }
         };
@@ -83,6 +87,8 @@ impl Gpu {
                     Err(e) => errors.push(SettingError {
                         msg: format!("RyzenAdj get_fast_value() err: {}", e),
                         setting: SettingVariant::Gpu,
+                        kind: SettingErrorKind::Backend,
+                        target: Some("get_fast_value".to_owned()),
                     }),
                 }
             }
@@ -90,6 +96,8 @@ impl Gpu {
                 .map_err(|e| SettingError {
                     msg: format!("RyzenAdj set_fast_limit({}) err: {}", *fast_ppt, e),
                     setting: SettingVariant::Gpu,
+                    kind: SettingErrorKind::Backend,
+                    target: Some("set_fast_limit".to_owned()),
                 })
                 .unwrap_or_else(|e| errors.push(e));
         } else if let Some(fast_ppt) = &self.state.old_fast_ppt {
@@ -97,6 +105,8 @@ impl Gpu {
                 .map_err(|e| SettingError {
                     msg: format!("RyzenAdj set_fast_limit({}) err: {}", *fast_ppt, e),
                     setting: SettingVariant::Gpu,
+                    kind: SettingErrorKind::Backend,
+                    target: Some("set_fast_limit".to_owned()),
                 })
                 .unwrap_or_else(|e| errors.push(e));
             self.state.old_fast_ppt = None;
@@ -104,10 +114,12 @@ impl Gpu {
         if let Some(slow_ppt) = &self.generic.slow_ppt {
             if self.state.old_slow_ppt.is_none() {
                 match lock.get_slow_value() {
-                    Ok(val) => self.state.old_fast_ppt = Some(val as _),
+                    Ok(val) => self.state.old_slow_ppt = Some(val as _),
                     Err(e) => errors.push(SettingError {
                         msg: format!("RyzenAdj get_slow_value() err: {}", e),
                         setting: SettingVariant::Gpu,
+                        kind: SettingErrorKind::Backend,
+                        target: Some("get_slow_value".to_owned()),
                     }),
                 }
             }
@@ -115,6 +127,8 @@ impl Gpu {
                 .map_err(|e| SettingError {
                     msg: format!("RyzenAdj set_slow_limit({}) err: {}", *slow_ppt, e),
                     setting: SettingVariant::Gpu,
+                    kind: SettingErrorKind::Backend,
+                    target: Some("set_slow_limit".to_owned()),
                 })
                 .unwrap_or_else(|e| errors.push(e));
         } else if let Some(slow_ppt) = &self.state.old_slow_ppt {
@@ -122,6 +136,8 @@ impl Gpu {
                 .map_err(|e| SettingError {
                     msg: format!("RyzenAdj set_slow_limit({}) err: {}", *slow_ppt, e),
                     setting: SettingVariant::Gpu,
+                    kind: SettingErrorKind::Backend,
+                    target: Some("set_slow_limit".to_owned()),
                 })
                 .unwrap_or_else(|e| errors.push(e));
             self.state.old_slow_ppt = None;
@@ -133,6 +149,8 @@ impl Gpu {
                     .map_err(|e| SettingError {
                         msg: format!("RyzenAdj set_max_gfxclk_freq({}) err: {}", max, e),
                         setting: SettingVariant::Gpu,
+                        kind: SettingErrorKind::Backend,
+                        target: Some("set_max_gfxclk_freq".to_owned()),
                     })
                     .unwrap_or_else(|e| errors.push(e));
             }
@@ -141,6 +159,8 @@ impl Gpu {
                     .map_err(|e| SettingError {
                         msg: format!("RyzenAdj set_min_gfxclk_freq({}) err: {}", min, e),
                         setting: SettingVariant::Gpu,
+                        kind: SettingErrorKind::Backend,
+                        target: Some("set_min_gfxclk_freq".to_owned()),
                     })
                     .unwrap_or_else(|e| errors.push(e));
             }
@@ -156,6 +176,8 @@ impl Gpu {
                                 max_limits.max, e
                             ),
                             setting: SettingVariant::Gpu,
+                            kind: SettingErrorKind::Backend,
+                            target: Some("set_max_gfxclk_freq".to_owned()),
                         })
                         .unwrap_or_else(|e| errors.push(e));
                     lock.set_min_gfxclk_freq(min_limits.min as _)
@@ -165,6 +187,8 @@ impl Gpu {
                                 min_limits.min, e
                             ),
                             setting: SettingVariant::Gpu,
+                            kind: SettingErrorKind::Backend,
+                            target: Some("set_min_gfxclk_freq".to_owned()),
                         })
                         .unwrap_or_else(|e| errors.push(e));
                 }
@@ -186,6 +210,8 @@ impl Gpu {
                 return Err(vec![SettingError {
                     msg: "RyzenAdj unavailable".to_owned(),
                     setting: SettingVariant::Gpu,
+                    kind: SettingErrorKind::Unsupported,
+                    target: None,
                 }]);
             }
         };
@@ -195,6 +221,8 @@ impl Gpu {
                 return Err(vec![SettingError {
                     msg: format!("RyzenAdj lock acquire failed: {}", e),
                     setting: SettingVariant::Gpu,
+                    kind: SettingErrorKind::Backend,
+                    target: None,
                 }]);
             }
         };
@@ -204,6 +232,8 @@ impl Gpu {
                 .map_err(|e| SettingError {
                     msg: format!("RyzenAdj set_fast_limit({}) err: {}", *fast_ppt, e),
                     setting: SettingVariant::Gpu,
+                    kind: SettingErrorKind::Backend,
+                    target: Some("set_fast_limit".to_owned()),
                 })
                 .unwrap_or_else(|e| errors.push(e));
         }
@@ -212,6 +242,8 @@ impl Gpu {
                 .map_err(|e| SettingError {
                     msg: format!("RyzenAdj set_slow_limit({}) err: {}", *slow_ppt, e),
                     setting: SettingVariant::Gpu,
+                    kind: SettingErrorKind::Backend,
+                    target: Some("set_slow_limit".to_owned()),
                 })
                 .unwrap_or_else(|e| errors.push(e));
         }
@@ -221,6 +253,8 @@ impl Gpu {
                     .map_err(|e| SettingError {
                         msg: format!("RyzenAdj set_max_gfxclk_freq({}) err: {}", max, e),
                         setting: SettingVariant::Gpu,
+                        kind: SettingErrorKind::Backend,
+                        target: Some("set_max_gfxclk_freq".to_owned()),
                     })
                     .unwrap_or_else(|e| errors.push(e));
             }
@@ -229,6 +263,8 @@ impl Gpu {
                     .map_err(|e| SettingError {
                         msg: format!("RyzenAdj set_min_gfxclk_freq({}) err: {}", min, e),
                         setting: SettingVariant::Gpu,
+                        kind: SettingErrorKind::Backend,
+                        target: Some("set_min_gfxclk_freq".to_owned()),
                     })
                     .unwrap_or_else(|e| errors.push(e));
             }
@@ -282,6 +318,78 @@ impl TGpu for Gpu {
         self.generic.slow_memory()
     }
 
+    fn actual(&self) -> crate::api::GpuActual {
+        let ppt = self
+            .implementor
+            .as_ref()
+            .and_then(|mutex| mutex.lock().ok())
+            .and_then(|lock| {
+                lock.refresh()
+                    .map_err(|e| log::warn!("RyzenAdj refresh() err: {}", e))
+                    .ok()?;
+                // RyzenAdj reports W, while limits are set in mW
+                let to_mw = |x: f32| (x * 1000.0).round() as u64;
+                Some((
+                    lock.get_fast_limit().ok().map(to_mw),
+                    lock.get_slow_limit().ok().map(to_mw),
+                ))
+            })
+            .unwrap_or((None, None));
+        // RyzenAdj can't read back gfxclk limits
+        crate::api::GpuActual::new(self.get_ppt(), self.get_clock_limits(), ppt, None)
+    }
+
+    fn reapply(&mut self, actual: &crate::api::GpuActual) -> Result<(), Vec<SettingError>> {
+        if !(actual.fast_ppt.drift || actual.slow_ppt.drift) {
+            return Ok(());
+        }
+        let lock = match self.implementor.as_ref().map(|mutex| mutex.lock()) {
+            Some(Ok(x)) => x,
+            Some(Err(e)) => {
+                return Err(vec![SettingError {
+                    msg: format!("RyzenAdj lock acquire failed: {}", e),
+                    setting: SettingVariant::Gpu,
+                    kind: SettingErrorKind::Backend,
+                    target: None,
+                }]);
+            }
+            None => {
+                return Err(vec![SettingError {
+                    msg: "RyzenAdj unavailable".to_owned(),
+                    setting: SettingVariant::Gpu,
+                    kind: SettingErrorKind::Unsupported,
+                    target: None,
+                }]);
+            }
+        };
+        let mut errors = Vec::new();
+        if let (true, Some(fast_ppt)) = (actual.fast_ppt.drift, actual.fast_ppt.desired) {
+            lock.set_fast_limit(fast_ppt as _)
+                .map_err(|e| SettingError {
+                    msg: format!("RyzenAdj set_fast_limit({}) err: {}", fast_ppt, e),
+                    setting: SettingVariant::Gpu,
+                    kind: SettingErrorKind::Backend,
+                    target: Some("set_fast_limit".to_owned()),
+                })
+                .unwrap_or_else(|e| errors.push(e));
+        }
+        if let (true, Some(slow_ppt)) = (actual.slow_ppt.drift, actual.slow_ppt.desired) {
+            lock.set_slow_limit(slow_ppt as _)
+                .map_err(|e| SettingError {
+                    msg: format!("RyzenAdj set_slow_limit({}) err: {}", slow_ppt, e),
+                    setting: SettingVariant::Gpu,
+                    kind: SettingErrorKind::Backend,
+                    target: Some("set_slow_limit".to_owned()),
+                })
+                .unwrap_or_else(|e| errors.push(e));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     fn provider(&self) -> crate::persist::DriverJson {
         crate::persist::DriverJson::GenericAMD
     }
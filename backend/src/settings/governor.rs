@@ -0,0 +1,395 @@
+//! Built-in thermal safety layer.
+//!
+//! The GPU and CPU drivers are wrapped, so that while the device is too hot they apply lower
+//! PPT and max clock values than the user's. The user's values are never changed, so they are
+//! what gets saved and they are applied again once things cool down.
+
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Arc;
+
+use crate::api::{ActualValue, RangeLimit};
+use crate::persist::ThermalGovernorJson;
+
+use super::{MinMax, OnPowerEvent, OnResume, OnSet, PowerMode, SettingError, TCpu, TCpus, TGpu};
+
+/// Highest throttle level
+pub const MAX_LEVEL: u8 = 4;
+/// Fraction of the user's values which is taken off per throttle level
+const STEP: f64 = 0.15;
+
+#[derive(Debug, Default)]
+struct Shared {
+    level: AtomicU8,
+    /// Settings were applied without throttling (i.e. on resume) while throttled
+    needs_reapply: AtomicBool,
+}
+
+impl Shared {
+    fn factor(&self) -> Option<f64> {
+        match self.level.load(Ordering::SeqCst) {
+            0 => None,
+            level => Some(1.0 - STEP * level as f64),
+        }
+    }
+}
+
+/// Temperature readings the governor made its last decision from
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThermalReadings {
+    pub apu: Option<f64>,
+    pub battery: Option<f64>,
+}
+
+#[derive(Debug)]
+pub struct ThermalGovernor {
+    pub config: ThermalGovernorJson,
+    pub readings: ThermalReadings,
+    shared: Arc<Shared>,
+}
+
+impl ThermalGovernor {
+    pub fn new(config: ThermalGovernorJson) -> Self {
+        Self {
+            config,
+            readings: ThermalReadings::default(),
+            shared: Arc::new(Shared::default()),
+        }
+    }
+
+    /// Create a governor with the saved configuration
+    pub fn load() -> Self {
+        let path = crate::utility::settings_dir().join(crate::consts::THERMAL_GOVERNOR_FILE);
+        Self::new(ThermalGovernorJson::open_or_default(path))
+    }
+
+    /// Stop throttling right away, i.e. before resetting everything
+    pub fn release(&self) {
+        self.shared.level.store(0, Ordering::SeqCst);
+    }
+
+    pub fn level(&self) -> u8 {
+        self.shared.level.load(Ordering::SeqCst)
+    }
+
+    pub fn wrap_gpu(&self, gpu: Box<dyn TGpu>) -> Box<dyn TGpu> {
+        Box::new(ThrottledGpu {
+            inner: gpu,
+            shared: self.shared.clone(),
+        })
+    }
+
+    pub fn wrap_cpus(&self, cpus: Box<dyn TCpus>) -> Box<dyn TCpus> {
+        Box::new(ThrottledCpus {
+            inner: cpus,
+            shared: self.shared.clone(),
+        })
+    }
+
+    /// Update the throttle level from new temperature readings.
+    /// Returns true when the settings need to be applied again.
+    pub fn update(&mut self, readings: ThermalReadings) -> bool {
+        self.readings = readings;
+        let old_level = self.level();
+        let new_level = if !self.config.enabled {
+            0
+        } else if self.is_above(0.0) {
+            (old_level + 1).min(MAX_LEVEL)
+        } else if old_level > 0 && !self.is_above(-self.config.hysteresis) {
+            old_level - 1
+        } else {
+            old_level
+        };
+        self.shared.level.store(new_level, Ordering::SeqCst);
+        if new_level > old_level {
+            log::warn!(
+                "Thermal governor throttling to level {} (APU {:?}C, battery {:?}C)",
+                new_level,
+                readings.apu,
+                readings.battery
+            );
+        } else if new_level < old_level {
+            log::info!(
+                "Thermal governor backing off to level {} (APU {:?}C, battery {:?}C)",
+                new_level,
+                readings.apu,
+                readings.battery
+            );
+        }
+        let needs_reapply = self.shared.needs_reapply.swap(false, Ordering::SeqCst);
+        new_level != old_level || (new_level != 0 && needs_reapply)
+    }
+
+    /// Whether any temperature is above its ceiling plus `offset`
+    fn is_above(&self, offset: f64) -> bool {
+        self.readings
+            .apu
+            .map(|t| t > self.config.apu_ceiling + offset)
+            .unwrap_or(false)
+            || self
+                .readings
+                .battery
+                .map(|t| t > self.config.battery_ceiling + offset)
+                .unwrap_or(false)
+    }
+}
+
+/// Lower `value` (or the highest allowed value, if it's unset) by `factor`, staying within `limit`
+fn throttle(value: Option<u64>, limit: &Option<RangeLimit<u64>>, factor: f64) -> Option<u64> {
+    let max = value.or_else(|| limit.as_ref().map(|l| l.max))?;
+    let throttled = (max as f64 * factor) as u64;
+    Some(match limit {
+        Some(limit) => throttled.max(limit.min).min(max),
+        None => throttled,
+    })
+}
+
+fn throttle_clocks(
+    clocks: Option<&MinMax<u64>>,
+    max_limit: &Option<RangeLimit<u64>>,
+    factor: f64,
+) -> Option<MinMax<u64>> {
+    let max = throttle(clocks.and_then(|c| c.max), max_limit, factor)?;
+    Some(MinMax {
+        min: clocks.and_then(|c| c.min).map(|min| min.min(max)),
+        max: Some(max),
+    })
+}
+
+#[derive(Debug)]
+struct ThrottledGpu {
+    inner: Box<dyn TGpu>,
+    shared: Arc<Shared>,
+}
+
+impl OnSet for ThrottledGpu {
+    fn on_set(&mut self) -> Result<(), Vec<SettingError>> {
+        let factor = if let Some(x) = self.shared.factor() {
+            x
+        } else {
+            return self.inner.on_set();
+        };
+        let limits = self.inner.limits();
+        let (fast_ppt, slow_ppt) = self.inner.get_ppt();
+        let clocks = self.inner.get_clock_limits().cloned();
+        self.inner.ppt(
+            throttle(fast_ppt, &limits.fast_ppt_limits, factor),
+            throttle(slow_ppt, &limits.slow_ppt_limits, factor),
+        );
+        if let Some(throttled) = throttle_clocks(clocks.as_ref(), &limits.clock_max_limits, factor) {
+            self.inner.clock_limits(Some(throttled));
+        }
+        let result = self.inner.on_set();
+        // keep the user's values
+        self.inner.ppt(fast_ppt, slow_ppt);
+        self.inner.clock_limits(clocks);
+        result
+    }
+}
+
+impl OnResume for ThrottledGpu {
+    fn on_resume(&self) -> Result<(), Vec<SettingError>> {
+        if self.shared.factor().is_some() {
+            self.shared.needs_reapply.store(true, Ordering::SeqCst);
+        }
+        self.inner.on_resume()
+    }
+}
+
+impl OnPowerEvent for ThrottledGpu {
+    fn on_power_event(&mut self, new_mode: PowerMode) -> Result<(), Vec<SettingError>> {
+        self.inner.on_power_event(new_mode)
+    }
+}
+
+impl TGpu for ThrottledGpu {
+    fn limits(&self) -> crate::api::GpuLimits {
+        self.inner.limits()
+    }
+
+    fn json(&self) -> crate::persist::GpuJson {
+        self.inner.json()
+    }
+
+    fn ppt(&mut self, fast: Option<u64>, slow: Option<u64>) {
+        self.inner.ppt(fast, slow)
+    }
+
+    fn get_ppt(&self) -> (Option<u64>, Option<u64>) {
+        self.inner.get_ppt()
+    }
+
+    fn clock_limits(&mut self, limits: Option<MinMax<u64>>) {
+        self.inner.clock_limits(limits)
+    }
+
+    fn get_clock_limits(&self) -> Option<&MinMax<u64>> {
+        self.inner.get_clock_limits()
+    }
+
+    fn slow_memory(&mut self) -> &mut bool {
+        self.inner.slow_memory()
+    }
+
+    fn load_json(&mut self, json: crate::persist::GpuJson) {
+        self.inner.load_json(json)
+    }
+
+    fn actual(&self) -> crate::api::GpuActual {
+        let actual = self.inner.actual();
+        let factor = if let Some(x) = self.shared.factor() {
+            x
+        } else {
+            return actual;
+        };
+        // while throttled, the hardware should be using the throttled values
+        let limits = self.inner.limits();
+        let desired_ppt = (
+            throttle(actual.fast_ppt.desired, &limits.fast_ppt_limits, factor),
+            throttle(actual.slow_ppt.desired, &limits.slow_ppt_limits, factor),
+        );
+        let clocks = MinMax {
+            min: actual.clock_min.desired,
+            max: actual.clock_max.desired,
+        };
+        let desired_clocks = throttle_clocks(Some(&clocks), &limits.clock_max_limits, factor);
+        let mut throttled = crate::api::GpuActual::new(
+            desired_ppt,
+            desired_clocks.as_ref(),
+            (actual.fast_ppt.actual, actual.slow_ppt.actual),
+            Some(MinMax {
+                min: actual.clock_min.actual,
+                max: actual.clock_max.actual,
+            }),
+        );
+        throttled.performance_level = actual.performance_level;
+        throttled
+    }
+
+    fn reapply(&mut self, actual: &crate::api::GpuActual) -> Result<(), Vec<SettingError>> {
+        // desired values are already the throttled ones
+        self.inner.reapply(actual)
+    }
+
+    fn provider(&self) -> crate::persist::DriverJson {
+        self.inner.provider()
+    }
+}
+
+#[derive(Debug)]
+struct ThrottledCpus {
+    inner: Box<dyn TCpus>,
+    shared: Arc<Shared>,
+}
+
+impl OnSet for ThrottledCpus {
+    fn on_set(&mut self) -> Result<(), Vec<SettingError>> {
+        let factor = if let Some(x) = self.shared.factor() {
+            x
+        } else {
+            return self.inner.on_set();
+        };
+        let limits = self.inner.limits();
+        let mut originals = Vec::with_capacity(limits.cpus.len());
+        for (cpu, cpu_limits) in self.inner.cpus().into_iter().zip(limits.cpus.iter()) {
+            let clocks = cpu.get_clock_limits().cloned();
+            if let Some(throttled) =
+                throttle_clocks(clocks.as_ref(), &cpu_limits.clock_max_limits, factor)
+            {
+                cpu.clock_limits(Some(throttled));
+            }
+            originals.push(clocks);
+        }
+        let result = self.inner.on_set();
+        // keep the user's values
+        for (cpu, clocks) in self.inner.cpus().into_iter().zip(originals) {
+            cpu.clock_limits(clocks);
+        }
+        result
+    }
+}
+
+impl OnResume for ThrottledCpus {
+    fn on_resume(&self) -> Result<(), Vec<SettingError>> {
+        if self.shared.factor().is_some() {
+            self.shared.needs_reapply.store(true, Ordering::SeqCst);
+        }
+        self.inner.on_resume()
+    }
+}
+
+impl OnPowerEvent for ThrottledCpus {
+    fn on_power_event(&mut self, new_mode: PowerMode) -> Result<(), Vec<SettingError>> {
+        self.inner.on_power_event(new_mode)
+    }
+}
+
+impl TCpus for ThrottledCpus {
+    fn limits(&self) -> crate::api::CpusLimits {
+        self.inner.limits()
+    }
+
+    fn json(&self) -> Vec<crate::persist::CpuJson> {
+        self.inner.json()
+    }
+
+    fn cpus(&mut self) -> Vec<&mut dyn TCpu> {
+        self.inner.cpus()
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn smt(&mut self) -> &'_ mut bool {
+        self.inner.smt()
+    }
+
+    fn actual(&self) -> crate::api::CpusActual {
+        let mut actual = self.inner.actual();
+        let factor = if let Some(x) = self.shared.factor() {
+            x
+        } else {
+            return actual;
+        };
+        let limits = self.inner.limits();
+        for (cpu, cpu_limits) in actual.cpus.iter_mut().zip(limits.cpus.iter()) {
+            let clocks = MinMax {
+                min: cpu.clock_min.desired,
+                max: cpu.clock_max.desired,
+            };
+            let throttled = throttle_clocks(Some(&clocks), &cpu_limits.clock_max_limits, factor);
+            cpu.clock_min = ActualValue::new(
+                throttled.as_ref().and_then(|x| x.min),
+                cpu.clock_min.actual,
+            );
+            cpu.clock_max = ActualValue::new(
+                throttled.as_ref().and_then(|x| x.max),
+                cpu.clock_max.actual,
+            );
+        }
+        actual
+    }
+
+    fn reapply(&mut self, actual: &crate::api::CpusActual) -> Result<(), Vec<SettingError>> {
+        // desired values are already the throttled ones
+        self.inner.reapply(actual)
+    }
+
+    fn provider(&self) -> crate::persist::DriverJson {
+        self.inner.provider()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttle_test() {
+        let limit = Some(RangeLimit { min: 3, max: 15 });
+        assert_eq!(throttle(Some(10), &limit, 0.5), Some(5));
+        assert_eq!(throttle(None, &limit, 0.4), Some(6));
+        assert_eq!(throttle(Some(4), &limit, 0.5), Some(3));
+        assert_eq!(throttle(None, &None, 0.5), None);
+    }
+}
@@ -0,0 +1,93 @@
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Maximum run time of a hook command, unless the profile specifies one
+pub const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+const POLL_PERIOD: Duration = Duration::from_millis(50);
+
+/// Run an event hook command in the background, with `env` as extra environment variables.
+/// The command is killed if it runs for longer than `timeout`;
+/// its exit status and stderr end up in the log.
+pub fn spawn_hook(event: &'static str, command: String, env: Vec<(String, String)>, timeout: Duration) {
+    let spawn_result = thread::Builder::new()
+        .name(format!("{}_hook", event))
+        .spawn(move || run_hook(event, &command, env, timeout));
+    if let Err(e) = spawn_result {
+        log::error!("Failed to start {} event thread: {}", event, e);
+    }
+}
+
+fn run_hook(event: &str, command: &str, env: Vec<(String, String)>, timeout: Duration) {
+    log::debug!("Running {} event command `{}`", event, command);
+    let mut child = match Command::new("/bin/bash")
+        .args(["-c", command])
+        .envs(env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(x) => x,
+        Err(e) => {
+            log::error!("Failed to start {} event command `{}`: {}", event, command, e);
+            return;
+        }
+    };
+    // read stderr concurrently, so a chatty command can't fill the pipe and stall
+    let stderr_reader = child.stderr.take().map(|mut stderr| {
+        thread::spawn(move || {
+            let mut output = String::new();
+            stderr.read_to_string(&mut output).map(|_| output)
+        })
+    });
+    let start = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if start.elapsed() > timeout => {
+                log::warn!(
+                    "{} event command `{}` timed out after {}s, killing it",
+                    event,
+                    command,
+                    timeout.as_secs_f32()
+                );
+                if let Err(e) = child.kill() {
+                    log::error!("Failed to kill {} event command: {}", event, e);
+                }
+                let _ = child.wait();
+                break None;
+            }
+            Ok(None) => thread::sleep(POLL_PERIOD),
+            Err(e) => {
+                log::error!("Failed to wait for {} event command: {}", event, e);
+                break None;
+            }
+        }
+    };
+    let stderr = stderr_reader
+        .and_then(|reader| reader.join().ok())
+        .and_then(|output| output.ok())
+        .unwrap_or_default();
+    match status {
+        Some(status) if status.success() => {
+            log::info!("{} event command completed successfully", event);
+            if !stderr.trim().is_empty() {
+                log::info!("{} event command stderr:\n{}", event, stderr.trim_end());
+            }
+        }
+        Some(status) => {
+            log::error!("{} event command `{}` failed ({})", event, command, status);
+            if !stderr.trim().is_empty() {
+                log::error!("{} event command stderr:\n{}", event, stderr.trim_end());
+            }
+        }
+        None => {
+            if !stderr.trim().is_empty() {
+                log::warn!("{} event command stderr:\n{}", event, stderr.trim_end());
+            }
+        }
+    }
+}
+
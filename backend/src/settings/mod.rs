@@ -1,22 +1,34 @@
 mod detect;
 pub mod driver;
+mod enforcer;
 mod error;
 mod general;
+mod governor;
+mod hooks;
 mod min_max;
+mod original;
+mod rules;
+mod thermal;
 mod traits;
 mod util;
+mod validate;
 
 pub mod generic;
 pub mod generic_amd;
 pub mod steam_deck;
 pub mod unknown;
 
-pub use detect::{auto_detect0, auto_detect_provider, limits_worker::spawn as limits_worker_spawn, get_dev_messages};
+pub use detect::{auto_detect0, auto_detect_provider, device_fingerprint, limits_worker::spawn as limits_worker_spawn, get_dev_messages};
 pub use driver::Driver;
+pub use enforcer::Enforcer;
 pub use general::{General, SettingVariant, Settings};
 pub use min_max::{min_max_from_json, MinMax};
+pub use original::OriginalState;
+pub use thermal::read_temperatures;
+pub use util::read_charger_online;
+pub use validate::check_limits;
 
-pub use error::SettingError;
+pub use error::{SettingError, SettingErrorKind};
 pub use traits::{OnPowerEvent, OnResume, OnSet, PowerMode, TBattery, TCpu, TCpus, TGeneral, TGpu};
 
 #[cfg(test)]
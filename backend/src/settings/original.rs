@@ -0,0 +1,152 @@
+//! Hardware state from before PowerTools changed anything, so that it can be put back on exit.
+
+use std::path::{Path, PathBuf};
+
+use super::{SettingError, SettingErrorKind, SettingVariant};
+
+const CPU_ROOT: &str = "/sys/devices/system/cpu";
+const DRM_ROOT: &str = "/sys/class/drm";
+const HWMON_ROOT: &str = "/sys/class/hwmon";
+const POWER_SUPPLY_ROOT: &str = "/sys/class/power_supply";
+
+const CPUFREQ_ATTRIBUTES: &[&str] = &["scaling_governor", "scaling_max_freq", "scaling_min_freq"];
+/// PPT and charge limit
+const HWMON_ATTRIBUTES: &[&str] = &["power1_cap", "power2_cap", "max_battery_charge_level"];
+const POWER_SUPPLY_ATTRIBUTES: &[&str] = &["charge_control_end_threshold", "charge_behaviour"];
+
+/// Values of the sysfs attributes which the drivers write to, as they were on startup.
+/// Attributes which can't be read back (e.g. the Steam Deck's charge rate) are left to the drivers to reset.
+#[derive(Debug, Clone, Default)]
+pub struct OriginalState {
+    values: Vec<(PathBuf, String)>,
+}
+
+impl OriginalState {
+    /// Read the current state. This should happen before any settings are applied.
+    pub fn take() -> Self {
+        let mut state = Self::default();
+        // order matters: CPUs have to be online to restore their cpufreq attributes
+        state.read(Path::new(CPU_ROOT).join("smt/control"));
+        let cpus = list_dir(CPU_ROOT, |name| {
+            name.strip_prefix("cpu")
+                .map(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+                .unwrap_or(false)
+        });
+        for cpu in &cpus {
+            state.read(cpu.join("online"));
+        }
+        for cpu in &cpus {
+            for attribute in CPUFREQ_ATTRIBUTES {
+                state.read(cpu.join("cpufreq").join(attribute));
+            }
+        }
+        for card in list_dir(DRM_ROOT, |name| name.starts_with("card") && !name.contains('-')) {
+            state.read(card.join("device/power_dpm_force_performance_level"));
+        }
+        for hwmon in list_dir(HWMON_ROOT, |_| true) {
+            for attribute in HWMON_ATTRIBUTES {
+                state.read(hwmon.join(attribute));
+            }
+        }
+        for psu in list_dir(POWER_SUPPLY_ROOT, |_| true) {
+            for attribute in POWER_SUPPLY_ATTRIBUTES {
+                state.read(psu.join(attribute));
+            }
+        }
+        log::info!("Saved {} original hardware values", state.values.len());
+        log::debug!("Original hardware values: {:?}", state.values);
+        state
+    }
+
+    fn read(&mut self, path: PathBuf) {
+        if let Ok(value) = std::fs::read_to_string(&path) {
+            if let Some(value) = restorable_value(&value) {
+                self.values.push((path, value));
+            }
+        }
+    }
+
+    /// Write back the original values
+    pub fn restore(&self) -> Result<(), Vec<SettingError>> {
+        let mut failed = Vec::new();
+        for (path, value) in &self.values {
+            if let Err(e) = write_if_changed(path, value) {
+                failed.push((path, value, e));
+            }
+        }
+        // some values depend on each other (e.g. a min clock can't be above the max clock), so try again once
+        let errors: Vec<SettingError> = failed
+            .into_iter()
+            .filter_map(|(path, value, first_err)| {
+                write_if_changed(path, value).err().map(|e| {
+                    log::debug!("First attempt to restore {} failed: {}", path.display(), first_err);
+                    SettingError {
+                        msg: format!("Failed to restore `{}` to `{}`: {}", path.display(), value, e),
+                        setting: SettingVariant::General,
+                        kind: SettingErrorKind::of_write(&e),
+                        target: Some(path.display().to_string()),
+                    }
+                })
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn list_dir(dir: &str, filter: impl Fn(&str) -> bool) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(x) => x
+            .filter_map(|e| e.ok())
+            .filter(|e| filter(&e.file_name().to_string_lossy()))
+            .map(|e| e.path())
+            .collect(),
+        Err(e) => {
+            log::debug!("Failed to list {}: {}", dir, e);
+            Vec::new()
+        }
+    };
+    entries.sort();
+    entries
+}
+
+fn write_if_changed(path: &Path, value: &str) -> std::io::Result<()> {
+    match std::fs::read_to_string(path) {
+        Ok(current) if restorable_value(&current).as_deref() == Some(value) => Ok(()),
+        _ => std::fs::write(path, value),
+    }
+}
+
+/// Value to write back for what was read from an attribute.
+/// Choice attributes (like `charge_behaviour`) list every option, with the selected one in brackets.
+fn restorable_value(read: &str) -> Option<String> {
+    let read = read.trim();
+    if read.is_empty() {
+        return None;
+    }
+    if let Some(start) = read.find('[') {
+        let end = read[start..].find(']')? + start;
+        Some(read[start + 1..end].to_owned())
+    } else {
+        Some(read.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restorable_value_test() {
+        assert_eq!(restorable_value("auto\n").as_deref(), Some("auto"));
+        assert_eq!(
+            restorable_value("[auto] inhibit-charge force-discharge\n").as_deref(),
+            Some("auto")
+        );
+        assert_eq!(restorable_value("auto [inhibit-charge]").as_deref(), Some("inhibit-charge"));
+        assert_eq!(restorable_value(" \n"), None);
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+
+use crate::persist::{OnEventJson, PartialSettingsJson, RuleJson};
+
+use super::{min_max_from_json, PowerMode, Settings};
+
+/// Degrees Celsius which a temperature rule has to cool down by before it's undone
+pub const DEFAULT_HYSTERESIS: f64 = 5.0;
+
+/// Power event condition, in the same format as battery events
+#[derive(Debug, Clone, Copy)]
+pub enum Trigger {
+    PluggedIn,
+    PluggedOut,
+    BatteryAbove(f64),
+    BatteryBelow(f64),
+    /// Degrees Celsius
+    TemperatureAbove(f64),
+}
+
+impl Trigger {
+    /// Parse a trigger like `plug-in`, `>80`, `<20` (battery percentages become a charge fraction)
+    /// or `temp>85`
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        match s {
+            "plug-in" => Some(Self::PluggedIn),
+            "plug-out" => Some(Self::PluggedOut),
+            s if s.starts_with("temp>") => s[5..]
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .map(Self::TemperatureAbove),
+            s if s.starts_with('>') => s[1..]
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .map(|x| Self::BatteryAbove(x / 100.0)),
+            s if s.starts_with('<') => s[1..]
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .map(|x| Self::BatteryBelow(x / 100.0)),
+            _ => None,
+        }
+    }
+
+    /// Whether the condition is met by the power event, or None if the event is unrelated
+    fn is_met(&self, mode: PowerMode) -> Option<bool> {
+        match (self, mode) {
+            (Self::PluggedIn, PowerMode::PluggedIn) => Some(true),
+            (Self::PluggedOut, PowerMode::PluggedOut) => Some(true),
+            (Self::BatteryAbove(exp), PowerMode::BatteryCharge(act)) => Some(act > *exp),
+            (Self::BatteryBelow(exp), PowerMode::BatteryCharge(act)) => Some(act < *exp),
+            _ => None,
+        }
+    }
+}
+
+/// Edge-triggering state of battery charge triggers, so they only fire when their threshold is crossed.
+/// This works the same way as Steam Deck battery events: plugging in or out re-arms everything.
+#[derive(Debug, Default)]
+pub struct TriggerState {
+    triggered: HashMap<String, bool>,
+}
+
+impl TriggerState {
+    pub fn reset(&mut self) {
+        self.triggered.clear();
+    }
+
+    /// Whether the trigger (identified by `key`) fires for this power event
+    pub fn fires(&mut self, key: &str, trigger: Trigger, mode: PowerMode) -> bool {
+        match (trigger, trigger.is_met(mode)) {
+            (_, None) => false,
+            (Trigger::PluggedIn | Trigger::PluggedOut, Some(met)) => met,
+            (_, Some(true)) => {
+                let is_triggered = self.triggered.entry(key.to_owned()).or_insert(false);
+                if *is_triggered {
+                    false
+                } else {
+                    *is_triggered = true;
+                    true
+                }
+            }
+            (_, Some(false)) => {
+                self.triggered.insert(key.to_owned(), false);
+                false
+            }
+        }
+    }
+}
+
+/// Event command set off by a power event
+#[derive(Debug, Clone, PartialEq)]
+pub struct FiredEvent {
+    pub event: &'static str,
+    pub command: String,
+    /// Battery charge trigger, for `on_charge` commands
+    pub trigger: Option<String>,
+}
+
+/// Everything a power event set off
+#[derive(Debug, Default)]
+pub struct Fired {
+    /// Rules whose trigger fired, in order
+    pub rules: Vec<usize>,
+    pub events: Vec<FiredEvent>,
+}
+
+impl TriggerState {
+    /// Evaluate the rules and the plug and charge event commands for a power event.
+    /// Plugging in or out re-arms all battery charge triggers.
+    pub fn power_event(&mut self, mode: PowerMode, rules: &[RuleJson], events: &OnEventJson) -> Fired {
+        let mut fired = Fired::default();
+        if let PowerMode::PluggedIn | PowerMode::PluggedOut = mode {
+            self.reset();
+        }
+        for (i, rule) in rules.iter().enumerate() {
+            let trigger = if let Some(x) = Trigger::parse(&rule.trigger) {
+                x
+            } else {
+                log::debug!("Ignoring rule with invalid trigger `{}`", rule.trigger);
+                continue;
+            };
+            if self.fires(&format!("rule{}:{}", i, rule.trigger), trigger, mode) {
+                log::info!("Rule #{} ({}) triggered", i, rule.trigger);
+                fired.rules.push(i);
+            }
+        }
+        let plug_event = match mode {
+            PowerMode::PluggedIn => Some(("on_plugged_in", &events.on_plugged_in)),
+            PowerMode::PluggedOut => Some(("on_unplugged", &events.on_unplugged)),
+            PowerMode::BatteryCharge(_) => None,
+        };
+        if let Some((event, Some(command))) = plug_event {
+            fired.events.push(FiredEvent {
+                event,
+                command: command.to_owned(),
+                trigger: None,
+            });
+        }
+        for charge in &events.on_charge {
+            let trigger = if let Some(x) = Trigger::parse(&charge.trigger) {
+                x
+            } else {
+                log::debug!("Ignoring charge event with invalid trigger `{}`", charge.trigger);
+                continue;
+            };
+            let key = format!("on_charge:{}:{}", charge.trigger, charge.command);
+            if self.fires(&key, trigger, mode) {
+                log::info!("Battery {} event handled", charge.trigger);
+                fired.events.push(FiredEvent {
+                    event: "on_charge",
+                    command: charge.command.to_owned(),
+                    trigger: Some(charge.trigger.to_owned()),
+                });
+            }
+        }
+        fired
+    }
+}
+
+/// Apply a partial settings change; it takes effect on the next `on_set()`
+pub fn apply_changes(settings: &mut Settings, changes: &PartialSettingsJson) {
+    if changes.fast_ppt.is_some() || changes.slow_ppt.is_some() {
+        let (fast_ppt, slow_ppt) = settings.gpu.get_ppt();
+        settings
+            .gpu
+            .ppt(changes.fast_ppt.or(fast_ppt), changes.slow_ppt.or(slow_ppt));
+    }
+    if let Some(clocks) = &changes.gpu_clock_limits {
+        settings.gpu.clock_limits(Some(min_max_from_json(
+            clocks.clone(),
+            crate::persist::LATEST_VERSION,
+        )));
+    }
+    if let Some(smt) = changes.smt {
+        *settings.cpus.smt() = smt;
+    }
+    if let Some(online) = &changes.cpus_online {
+        for (cpu, online) in settings.cpus.cpus().into_iter().zip(online) {
+            *cpu.online() = *online;
+        }
+    }
+    if let Some(governor) = &changes.governor {
+        for cpu in settings.cpus.cpus() {
+            cpu.governor(governor.to_owned());
+        }
+    }
+    if let Some(rate) = changes.charge_rate {
+        settings.battery.charge_rate(Some(rate));
+    }
+    if let Some(mode) = &changes.charge_mode {
+        settings.battery.charge_mode(Some(mode.to_owned()));
+    }
+    if let Some(limit) = changes.charge_limit {
+        settings.battery.charge_limit(Some(limit));
+    }
+}
+
+/// Values of the settings which a partial settings change is about to change, so that they can be restored
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    /// All of the GPU, since PPTs don't round-trip through `ppt()` on every driver
+    gpu: Option<crate::persist::GpuJson>,
+    cpus_online: Option<Vec<bool>>,
+    governors: Option<Vec<String>>,
+    smt: Option<bool>,
+    charge_rate: Option<Option<u64>>,
+    charge_mode: Option<Option<String>>,
+    charge_limit: Option<Option<f64>>,
+}
+
+impl Snapshot {
+    pub fn take(settings: &mut Settings, changes: &PartialSettingsJson) -> Self {
+        Self {
+            gpu: (changes.fast_ppt.is_some()
+                || changes.slow_ppt.is_some()
+                || changes.gpu_clock_limits.is_some())
+            .then(|| settings.gpu.json()),
+            cpus_online: changes
+                .cpus_online
+                .as_ref()
+                .map(|_| settings.cpus.cpus().into_iter().map(|c| *c.online()).collect()),
+            governors: changes.governor.as_ref().map(|_| {
+                settings
+                    .cpus
+                    .cpus()
+                    .into_iter()
+                    .map(|c| c.get_governor().to_owned())
+                    .collect()
+            }),
+            smt: changes.smt.map(|_| *settings.cpus.smt()),
+            charge_rate: changes.charge_rate.map(|_| settings.battery.get_charge_rate()),
+            charge_mode: changes
+                .charge_mode
+                .as_ref()
+                .map(|_| settings.battery.get_charge_mode()),
+            charge_limit: changes.charge_limit.map(|_| settings.battery.get_charge_limit()),
+        }
+    }
+
+    pub fn restore(self, settings: &mut Settings) {
+        if let Some(gpu) = self.gpu {
+            settings.gpu.load_json(gpu);
+        }
+        if let Some(smt) = self.smt {
+            *settings.cpus.smt() = smt;
+        }
+        if let Some(online) = self.cpus_online {
+            for (cpu, online) in settings.cpus.cpus().into_iter().zip(online) {
+                *cpu.online() = online;
+            }
+        }
+        if let Some(governors) = self.governors {
+            for (cpu, governor) in settings.cpus.cpus().into_iter().zip(governors) {
+                cpu.governor(governor);
+            }
+        }
+        if let Some(rate) = self.charge_rate {
+            settings.battery.charge_rate(rate);
+        }
+        if let Some(mode) = self.charge_mode {
+            settings.battery.charge_mode(mode);
+        }
+        if let Some(limit) = self.charge_limit {
+            settings.battery.charge_limit(limit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power_event_test() {
+        let rule = |trigger: &str| RuleJson {
+            trigger: trigger.to_owned(),
+            sensor: None,
+            hysteresis: None,
+            changes: PartialSettingsJson::default(),
+        };
+        assert!(Trigger::parse("/shrug").is_none());
+        assert!(matches!(Trigger::parse("temp>85"), Some(Trigger::TemperatureAbove(t)) if t == 85.0));
+        // temperature rules are checked separately, never by power events
+        let rules = vec![rule(">80"), rule("plug-out"), rule("temp>85")];
+        let events: OnEventJson = serde_json::from_value(serde_json::json!({
+            "on_save": null,
+            "on_load": null,
+            "on_set": null,
+            "on_resume": null,
+            "on_unplugged": "unplugged",
+            "on_charge": [
+                {"trigger": ">80", "command": "above"},
+                {"trigger": "<20", "command": "below"},
+            ],
+        }))
+        .unwrap();
+        let commands = |fired: &Fired| -> Vec<String> {
+            fired.events.iter().map(|e| e.command.clone()).collect()
+        };
+        let mut state = TriggerState::default();
+        let fired = state.power_event(PowerMode::BatteryCharge(0.5), &rules, &events);
+        assert!(fired.rules.is_empty() && fired.events.is_empty());
+        let fired = state.power_event(PowerMode::BatteryCharge(0.85), &rules, &events);
+        assert_eq!(fired.rules, vec![0]);
+        assert_eq!(commands(&fired), vec!["above"]);
+        assert_eq!(fired.events[0].trigger.as_deref(), Some(">80"));
+        // only fires again once re-armed
+        let fired = state.power_event(PowerMode::BatteryCharge(0.9), &rules, &events);
+        assert!(fired.rules.is_empty() && fired.events.is_empty());
+        state.power_event(PowerMode::BatteryCharge(0.5), &rules, &events);
+        let fired = state.power_event(PowerMode::BatteryCharge(0.81), &rules, &events);
+        assert_eq!(commands(&fired), vec!["above"]);
+        // unplugging re-arms everything
+        let fired = state.power_event(PowerMode::PluggedOut, &rules, &events);
+        assert_eq!(fired.rules, vec![1]);
+        assert_eq!(commands(&fired), vec!["unplugged"]);
+        let fired = state.power_event(PowerMode::BatteryCharge(0.81), &rules, &events);
+        assert_eq!(fired.rules, vec![0]);
+        assert_eq!(commands(&fired), vec!["above"]);
+        let fired = state.power_event(PowerMode::BatteryCharge(0.1), &rules, &events);
+        assert_eq!(commands(&fired), vec!["below"]);
+        assert!(state.power_event(PowerMode::PluggedIn, &rules, &events).events.is_empty());
+    }
+}
@@ -9,7 +9,7 @@ use super::util::ChargeMode;
 use crate::api::RangeLimit;
 use crate::persist::{BatteryEventJson, BatteryJson};
 use crate::settings::TBattery;
-use crate::settings::{OnPowerEvent, OnResume, OnSet, PowerMode, SettingError};
+use crate::settings::{OnPowerEvent, OnResume, OnSet, PowerMode, SettingError, SettingErrorKind};
 
 #[derive(Debug, Clone)]
 pub struct Battery {
@@ -135,6 +135,8 @@ impl EventInstruction {
                 .map_err(|e| SettingError {
                     msg: format!("Failed to set charge mode: {}", e),
                     setting: crate::settings::SettingVariant::Battery,
+                    kind: SettingErrorKind::Backend,
+                    target: Some("charge_mode".to_owned()),
                 })
                 .map(|_| ())
         } else {
@@ -153,6 +155,8 @@ impl EventInstruction {
                 |e| SettingError {
                     msg: format!("Failed to write to `{:?}`: {}", attr, e),
                     setting: crate::settings::SettingVariant::Battery,
+                    kind: SettingErrorKind::of_write(&e),
+                    target: Some(attr.path(&*self.sysfs_hwmon).display().to_string()),
                 },
             )
         } else {
@@ -350,6 +354,8 @@ impl Battery {
                 |e| SettingError {
                     msg: format!("Failed to write to `{}`: {}", path.display(), e),
                     setting: crate::settings::SettingVariant::Battery,
+                    kind: SettingErrorKind::of_write(&e),
+                    target: Some(path.display().to_string()),
                 },
             )
         } else if self.state.charge_rate_set {
@@ -364,6 +370,8 @@ impl Battery {
                 |e| SettingError {
                     msg: format!("Failed to write to `{}`: {}", path.display(), e),
                     setting: crate::settings::SettingVariant::Battery,
+                    kind: SettingErrorKind::of_write(&e),
+                    target: Some(path.display().to_string()),
                 },
             )
         } else {
@@ -378,6 +386,8 @@ impl Battery {
                 .map_err(|e| SettingError {
                     msg: format!("Failed to set charge mode: {}", e),
                     setting: crate::settings::SettingVariant::Battery,
+                    kind: SettingErrorKind::Backend,
+                    target: Some("charge_mode".to_owned()),
                 })
                 .map(|_| ())
         } else if self.state.charge_mode_set {
@@ -386,6 +396,8 @@ impl Battery {
                 .map_err(|e| SettingError {
                     msg: format!("Failed to set charge mode: {}", e),
                     setting: crate::settings::SettingVariant::Battery,
+                    kind: SettingErrorKind::Backend,
+                    target: Some("charge_mode".to_owned()),
                 })
                 .map(|_| ())
         } else {
@@ -417,6 +429,8 @@ impl Battery {
             Err(e) => Err(SettingError {
                 msg: format!("Failed to read from `{:?}`: {}", attr, e),
                 setting: crate::settings::SettingVariant::Battery,
+                kind: SettingErrorKind::of_read(&e),
+                target: Some(attr.path(&self.sysfs_bat).display().to_string()),
             }),
             // this value is in uA, while it's set in mA
             // so convert this to mA for consistency
@@ -436,6 +450,8 @@ impl Battery {
             Err(e) => Err(SettingError {
                 msg: format!("Failed to read from `{:?}`: {}", attr, e),
                 setting: crate::settings::SettingVariant::Battery,
+                kind: SettingErrorKind::of_read(&e),
+                target: Some(attr.path(&self.sysfs_bat).display().to_string()),
             }),
             // convert to Wh
             Ok(val) => Ok((val as f64) / 1000000.0 * BATTERY_VOLTAGE),
@@ -448,6 +464,8 @@ impl Battery {
             Err(e) => Err(SettingError {
                 msg: format!("Failed to read from `{:?}`: {}", attr, e),
                 setting: crate::settings::SettingVariant::Battery,
+                kind: SettingErrorKind::of_read(&e),
+                target: Some(attr.path(&self.sysfs_bat).display().to_string()),
             }),
             // convert to Wh
             Ok(val) => Ok((val as f64) / 1000000.0 * BATTERY_VOLTAGE),
@@ -460,6 +478,8 @@ impl Battery {
             Err(e) => Err(SettingError {
                 msg: format!("Failed to read from `{:?}`: {}", attr, e),
                 setting: crate::settings::SettingVariant::Battery,
+                kind: SettingErrorKind::of_read(&e),
+                target: Some(attr.path(&self.sysfs_bat).display().to_string()),
             }),
             // convert to Wh
             Ok(val) => Ok((val as f64) / 1000000.0 * BATTERY_VOLTAGE),
@@ -472,6 +492,8 @@ impl Battery {
             Err(e) => Err(SettingError {
                 msg: format!("Failed to read from `{:?}`: {}", attr, e),
                 setting: crate::settings::SettingVariant::Battery,
+                kind: SettingErrorKind::of_read(&e),
+                target: Some(attr.path(&*self.sysfs_hwmon).display().to_string()),
             }),
             // convert to V (from mV)
             Ok(val) => Ok((val as f64) / 1000.0),
@@ -484,6 +506,8 @@ impl Battery {
             Err(e) => Err(SettingError {
                 msg: format!("Failed to read `{:?}`: {}", attr, e),
                 setting: crate::settings::SettingVariant::Battery,
+                kind: SettingErrorKind::of_read(&e),
+                target: Some(attr.path(&*self.sysfs_hwmon).display().to_string()),
             }),
             Ok(val) => Ok((val as f64) / 1000.0), // mA -> A
         }
@@ -595,6 +619,8 @@ impl OnPowerEvent for Battery {
                                 SettingError {
                                     msg: format!("Failed to write to {:?}: {}", MAX_BATTERY_CHARGE_LEVEL_ATTR, e),
                                     setting: crate::settings::SettingVariant::Battery,
+                                    kind: SettingErrorKind::of_write(&e),
+                                    target: Some(MAX_BATTERY_CHARGE_LEVEL_ATTR.path(&*self.sysfs_hwmon).display().to_string()),
                                 }
                             ));
                     }
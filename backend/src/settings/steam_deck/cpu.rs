@@ -7,7 +7,7 @@ use super::POWER_DPM_FORCE_PERFORMANCE_LEVEL_MGMT;
 use crate::api::RangeLimit;
 use crate::persist::CpuJson;
 use crate::settings::{min_max_from_json, MinMax};
-use crate::settings::{OnResume, OnSet, SettingError};
+use crate::settings::{OnResume, OnSet, SettingError, SettingErrorKind};
 use crate::settings::{TCpu, TCpus};
 
 const CPU_PRESENT_PATH: &str = "/sys/devices/system/cpu/present";
@@ -36,6 +36,8 @@ impl OnSet for Cpus {
                     .map_err(|e| SettingError {
                         msg: format!("Failed to write `on` to `{}`: {}", CPU_SMT_PATH, e),
                         setting: crate::settings::SettingVariant::Cpu,
+                        kind: SettingErrorKind::of_write(&e),
+                        target: Some(CPU_SMT_PATH.to_owned()),
                     })
                     .unwrap_or_else(|e| errors.push(e));
             } else {
@@ -43,6 +45,8 @@ impl OnSet for Cpus {
                     .map_err(|e| SettingError {
                         msg: format!("Failed to write `off` to `{}`: {}", CPU_SMT_PATH, e),
                         setting: crate::settings::SettingVariant::Cpu,
+                        kind: SettingErrorKind::of_write(&e),
+                        target: Some(CPU_SMT_PATH.to_owned()),
                     })
                     .unwrap_or_else(|e| errors.push(e));
             }
@@ -223,6 +227,28 @@ impl TCpus for Cpus {
         &mut self.smt
     }
 
+    fn actual(&self) -> crate::api::CpusActual {
+        crate::settings::util::cpus_actual(
+            self.smt,
+            self.smt_capable,
+            self.cpus.iter().map(|x| x.actual()).collect(),
+        )
+    }
+
+    fn reapply(&mut self, actual: &crate::api::CpusActual) -> Result<(), Vec<SettingError>> {
+        let mut errors = Vec::new();
+        crate::settings::util::reapply_cpus(actual).unwrap_or_else(|mut e| errors.append(&mut e));
+        for (cpu, cpu_actual) in self.cpus.iter().zip(actual.cpus.iter()) {
+            cpu.reapply_clock_limits(cpu_actual)
+                .unwrap_or_else(|mut e| errors.append(&mut e));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     fn provider(&self) -> crate::persist::DriverJson {
         self.driver_mode.clone()
     }
@@ -303,6 +329,8 @@ impl Cpu {
                     &payload, CPU_CLOCK_LIMITS_ATTRIBUTE, e
                 ),
                 setting: crate::settings::SettingVariant::Cpu,
+                kind: SettingErrorKind::of_write(&e),
+                target: Some(self.sysfs.as_ref().join(CPU_CLOCK_LIMITS_ATTRIBUTE).display().to_string()),
             }
         })
     }
@@ -401,11 +429,36 @@ impl Cpu {
         }
     }
 
+    /// Write the desired clock limits again, if they drifted
+    fn reapply_clock_limits(&self, actual: &crate::api::CpuActual) -> Result<(), Vec<SettingError>> {
+        if !(actual.clock_min.drift || actual.clock_max.drift) {
+            return Ok(());
+        }
+        POWER_DPM_FORCE_PERFORMANCE_LEVEL_MGMT.enforce_level(&self.sysfs)?;
+        let mut errors = Vec::new();
+        if let Some(max) = actual.clock_max.desired {
+            self.set_clock_limit(self.index, max, ClockType::Max)
+                .unwrap_or_else(|e| errors.push(e));
+        }
+        if let Some(min) = actual.clock_min.desired {
+            self.set_clock_limit(self.index, min, ClockType::Min)
+                .unwrap_or_else(|e| errors.push(e));
+        }
+        self.set_confirm().unwrap_or_else(|e| errors.push(e));
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     fn set_confirm(&self) -> Result<(), SettingError> {
         self.sysfs.set(CPU_CLOCK_LIMITS_ATTRIBUTE.to_owned(), "c\n").map_err(|e| {
             SettingError {
                 msg: format!("Failed to write `c` to `{}`: {}", CPU_CLOCK_LIMITS_ATTRIBUTE, e),
                 setting: crate::settings::SettingVariant::Cpu,
+                kind: SettingErrorKind::of_write(&e),
+                target: Some(self.sysfs.as_ref().join(CPU_CLOCK_LIMITS_ATTRIBUTE).display().to_string()),
             }
         })
     }
@@ -440,6 +493,8 @@ impl Cpu {
                         &self.governor, &governor_path, e
                     ),
                     setting: crate::settings::SettingVariant::Cpu,
+                    kind: SettingErrorKind::of_write(&e),
+                    target: Some(governor_path.clone()),
                 }
             })
         } else {
@@ -457,6 +512,8 @@ impl Cpu {
                 .map_err(|e| SettingError {
                     msg: format!("Failed to write to `{}`: {}", &online_path, e),
                     setting: crate::settings::SettingVariant::Cpu,
+                    kind: SettingErrorKind::of_write(&e),
+                    target: Some(online_path.clone()),
                 })
                 .unwrap_or_else(|e| errors.push(e));
         }
@@ -586,6 +643,31 @@ impl TCpu for Cpu {
     fn get_clock_limits(&self) -> Option<&MinMax<u64>> {
         self.clock_limits.as_ref()
     }
+
+    fn actual(&self) -> crate::api::CpuActual {
+        let mut actual = crate::settings::util::cpu_actual(self.index, self.online, &self.governor);
+        // set_all() raises the min clock to the lowest allowed value
+        let desired = self.clock_limits.as_ref().map(|x| MinMax {
+            min: x.min.map(|min| min.max(self.limits.clock_min.min)),
+            max: x.max,
+        });
+        // the clocks of only one core are shown at a time, and two CPUs share a core
+        let clocks = std::fs::read_to_string(self.sysfs.as_ref().join(CPU_CLOCK_LIMITS_ATTRIBUTE))
+            .ok()
+            .and_then(|od| {
+                let (core, clocks) = super::util::od_clock_limits(&od, "CCLK_RANGE in Core")?;
+                (core.trim_end_matches(':').parse::<usize>().ok()? == self.index / 2).then_some(clocks)
+            });
+        actual.clock_min = crate::api::ActualValue::new(
+            desired.as_ref().and_then(|x| x.min),
+            clocks.as_ref().and_then(|x| x.min),
+        );
+        actual.clock_max = crate::api::ActualValue::new(
+            desired.as_ref().and_then(|x| x.max),
+            clocks.as_ref().and_then(|x| x.max),
+        );
+        actual
+    }
 }
 
 #[inline]
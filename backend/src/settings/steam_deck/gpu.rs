@@ -8,7 +8,7 @@ use crate::api::RangeLimit;
 use crate::persist::GpuJson;
 use crate::settings::TGpu;
 use crate::settings::{min_max_from_json, MinMax};
-use crate::settings::{OnResume, OnSet, SettingError};
+use crate::settings::{OnResume, OnSet, SettingError, SettingErrorKind};
 
 // usually in /sys/class/hwmon/hwmon4/<attribute>
 const SLOW_PPT_ATTRIBUTE: sysfuss::HwMonAttribute = sysfuss::HwMonAttribute::custom("power1_cap");
@@ -119,6 +119,8 @@ impl Gpu {
             SettingError {
                 msg: format!("Failed to write `{}` to `{}`: {}", &payload, path.display(), e),
                 setting: crate::settings::SettingVariant::Gpu,
+                kind: SettingErrorKind::of_write(&e),
+                target: Some(path.display().to_string()),
             }
         })
     }
@@ -129,6 +131,8 @@ impl Gpu {
             SettingError {
                 msg: format!("Failed to write `c` to `{}`: {}", path.display(), e),
                 setting: crate::settings::SettingVariant::Gpu,
+                kind: SettingErrorKind::of_write(&e),
+                target: Some(path.display().to_string()),
             }
         })
     }
@@ -187,6 +191,8 @@ impl Gpu {
                 SettingError {
                     msg: format!("Failed to write to `{}`: {}", path.display(), e),
                     setting: crate::settings::SettingVariant::Gpu,
+                    kind: SettingErrorKind::of_write(&e),
+                    target: Some(path.display().to_string()),
                 }
             })
         } else {
@@ -195,6 +201,8 @@ impl Gpu {
                 SettingError {
                     msg: format!("Failed to write to `{}`: {}", path.display(), e),
                     setting: crate::settings::SettingVariant::Gpu,
+                    kind: SettingErrorKind::of_write(&e),
+                    target: Some(path.display().to_string()),
                 }
             })
         }
@@ -245,6 +253,8 @@ impl Gpu {
                         fast_ppt, FAST_PPT_ATTRIBUTE, e
                     ),
                     setting: crate::settings::SettingVariant::Gpu,
+                    kind: SettingErrorKind::of_write(&e),
+                    target: Some(FAST_PPT_ATTRIBUTE.path(&self.sysfs_hwmon).display().to_string()),
                 })
                 .unwrap_or_else(|e| {
                     errors.push(e);
@@ -259,6 +269,8 @@ impl Gpu {
                         fast_ppt, FAST_PPT_ATTRIBUTE, e
                     ),
                     setting: crate::settings::SettingVariant::Gpu,
+                    kind: SettingErrorKind::of_write(&e),
+                    target: Some(FAST_PPT_ATTRIBUTE.path(&self.sysfs_hwmon).display().to_string()),
                 })
                 .unwrap_or_else(|e| {
                     errors.push(e);
@@ -274,6 +286,8 @@ impl Gpu {
                         slow_ppt, SLOW_PPT_ATTRIBUTE, e
                     ),
                     setting: crate::settings::SettingVariant::Gpu,
+                    kind: SettingErrorKind::of_write(&e),
+                    target: Some(SLOW_PPT_ATTRIBUTE.path(&self.sysfs_hwmon).display().to_string()),
                 })
                 .unwrap_or_else(|e| {
                     errors.push(e);
@@ -288,6 +302,8 @@ impl Gpu {
                         slow_ppt, SLOW_PPT_ATTRIBUTE, e
                     ),
                     setting: crate::settings::SettingVariant::Gpu,
+                    kind: SettingErrorKind::of_write(&e),
+                    target: Some(SLOW_PPT_ATTRIBUTE.path(&self.sysfs_hwmon).display().to_string()),
                 })
                 .unwrap_or_else(|e| {
                     errors.push(e);
@@ -427,6 +443,87 @@ impl TGpu for Gpu {
         &mut self.slow_memory
     }
 
+    fn load_json(&mut self, json: GpuJson) {
+        // PPTs are stored as-is, not in the units of ppt()
+        self.fast_ppt = json.fast_ppt;
+        self.slow_ppt = json.slow_ppt;
+        self.clock_limits = json
+            .clock_limits
+            .map(|x| min_max_from_json(x, crate::persist::LATEST_VERSION));
+        self.slow_memory = json.slow_memory;
+    }
+
+    fn actual(&self) -> crate::api::GpuActual {
+        let read_ppt = |attr| {
+            self.sysfs_hwmon
+                .attribute::<u64, _>(attr)
+                .ok()
+                .map(|x| x / self.limits.ppt_divisor)
+        };
+        let clocks = std::fs::read_to_string(GPU_CLOCK_LIMITS_ATTRIBUTE.path(&self.sysfs_card))
+            .ok()
+            .and_then(|od| super::util::od_clock_limits(&od, "OD_SCLK:").map(|(_, clocks)| clocks));
+        let mut actual = crate::api::GpuActual::new(
+            self.get_ppt(),
+            self.get_clock_limits(),
+            (read_ppt(FAST_PPT_ATTRIBUTE), read_ppt(SLOW_PPT_ATTRIBUTE)),
+            clocks,
+        );
+        // only manual matters; auto is the system's choice, not a setting
+        actual.performance_level = crate::api::ActualValue::new(
+            Some("manual".to_owned())
+                .filter(|_| POWER_DPM_FORCE_PERFORMANCE_LEVEL_MGMT.needs_manual()),
+            self.sysfs_card
+                .attribute::<String, _>(super::DPM_FORCE_LIMITS_ATTRIBUTE.to_owned())
+                .ok()
+                .map(|x| x.trim().to_owned()),
+        );
+        actual
+    }
+
+    fn reapply(&mut self, actual: &crate::api::GpuActual) -> Result<(), Vec<SettingError>> {
+        let mut errors = Vec::new();
+        for (ppt, attribute) in [
+            (&actual.fast_ppt, FAST_PPT_ATTRIBUTE),
+            (&actual.slow_ppt, SLOW_PPT_ATTRIBUTE),
+        ] {
+            if let (true, Some(ppt)) = (ppt.drift, ppt.desired) {
+                let ppt = ppt * self.limits.ppt_divisor;
+                self.sysfs_hwmon.set(attribute, ppt)
+                    .map_err(|e| SettingError {
+                        msg: format!("Failed to write `{}` to `{:?}`: {}", ppt, attribute, e),
+                        setting: crate::settings::SettingVariant::Gpu,
+                        kind: SettingErrorKind::of_write(&e),
+                        target: Some(attribute.path(&self.sysfs_hwmon).display().to_string()),
+                    })
+                    .unwrap_or_else(|e| errors.push(e));
+            }
+        }
+        let clocks_drift = actual.clock_min.drift || actual.clock_max.drift;
+        if actual.performance_level.drift || clocks_drift {
+            if let Err(mut e) = POWER_DPM_FORCE_PERFORMANCE_LEVEL_MGMT.enforce_level(&self.sysfs_card) {
+                // clock limits can't be written without manual control
+                errors.append(&mut e);
+                return Err(errors);
+            }
+        }
+        if clocks_drift {
+            // both limits are written, since the confirmation commits both anyway
+            if let Some(max) = actual.clock_max.desired {
+                self.set_clock_limit(max, ClockType::Max).unwrap_or_else(|e| errors.push(e));
+            }
+            if let Some(min) = actual.clock_min.desired {
+                self.set_clock_limit(min, ClockType::Min).unwrap_or_else(|e| errors.push(e));
+            }
+            self.set_confirm().unwrap_or_else(|e| errors.push(e));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     fn provider(&self) -> crate::persist::DriverJson {
         self.driver_mode.clone()
     }
@@ -7,7 +7,7 @@ use std::sync::atomic::{AtomicU64, Ordering};
 
 use sysfuss::{BasicEntityPath, SysEntityAttributesExt, SysAttribute};
 
-use crate::settings::SettingError;
+use crate::settings::{SettingError, SettingErrorKind};
 
 const DEFAULT_BITS: u64 = 0;
 
@@ -68,6 +68,8 @@ impl PDFPLManager {
                 vec![SettingError {
                     msg: format!("Failed to read `{}`: {}", path.display(), e),
                     setting: crate::settings::SettingVariant::General,
+                    kind: SettingErrorKind::of_read(&e),
+                    target: Some(path.display().to_string()),
                 }]
             })?;
         if mode != "manual" && needs {
@@ -78,6 +80,8 @@ impl PDFPLManager {
                     errors.push(SettingError {
                         msg: format!("Failed to write `manual` to `{}`: {}", path.display(), e),
                         setting: crate::settings::SettingVariant::General,
+                        kind: SettingErrorKind::of_write(&e),
+                        target: Some(path.display().to_string()),
                     })
                 })
                 .unwrap_or(());
@@ -89,6 +93,8 @@ impl PDFPLManager {
                     errors.push(SettingError {
                         msg: format!("Failed to write `auto` to `{}`: {}", path.display(), e),
                         setting: crate::settings::SettingVariant::General,
+                        kind: SettingErrorKind::of_write(&e),
+                        target: Some(path.display().to_string()),
                     })
                 })
                 .unwrap_or(());
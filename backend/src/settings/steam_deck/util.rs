@@ -17,6 +17,36 @@ pub fn card_also_has(card: &dyn sysfuss::SysEntity, extensions: &'static [&'stat
         .all(|ext| card.as_ref().join(ext).exists())
 }
 
+/// Clock limits (in MHz) from the `pp_od_clk_voltage` section whose header starts with `header`,
+/// along with the rest of the header line.
+/// The GPU's section is `OD_SCLK:`, while the CPU's is `CCLK_RANGE in Core<N>:` for the last core which was written to.
+pub fn od_clock_limits<'a>(
+    od: &'a str,
+    header: &str,
+) -> Option<(&'a str, crate::settings::MinMax<u64>)> {
+    let mut lines = od.lines().skip_while(|line| !line.starts_with(header));
+    let rest = lines.next()?[header.len()..].trim();
+    let mut limits = crate::settings::MinMax { min: None, max: None };
+    for line in lines {
+        let (level, value) = if let Some(x) = line.split_once(':') {
+            x
+        } else {
+            break;
+        };
+        let value = value
+            .trim()
+            .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+            .parse::<u64>()
+            .ok();
+        match level.trim() {
+            "0" => limits.min = value,
+            "1" => limits.max = value,
+            _ => break,
+        }
+    }
+    Some((rest, limits))
+}
+
 #[inline]
 fn write2(p0: u8, p1: u8) -> Result<usize, Error> {
     write_to(0x6c, 0x81)?;
@@ -136,3 +166,20 @@ pub enum Charge {
     Enable = 0,
     Disable = 4,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn od_clock_limits_test() {
+        let od = "OD_SCLK:\n0:        200Mhz\n1:       1600Mhz\nOD_RANGE:\nSCLK:     200Mhz       1600Mhz\nCCLK:    1400Mhz       3500Mhz\nCCLK_RANGE in Core2:\n0:       1400Mhz\n1:       2800Mhz\n";
+        let (rest, gpu) = od_clock_limits(od, "OD_SCLK:").unwrap();
+        assert_eq!(rest, "");
+        assert_eq!((gpu.min, gpu.max), (Some(200), Some(1600)));
+        let (core, cpu) = od_clock_limits(od, "CCLK_RANGE in Core").unwrap();
+        assert_eq!(core, "2:");
+        assert_eq!((cpu.min, cpu.max), (Some(1400), Some(2800)));
+        assert!(od_clock_limits(od, "OD_MCLK:").is_none());
+    }
+}
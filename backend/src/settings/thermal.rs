@@ -0,0 +1,91 @@
+use std::path::Path;
+
+use crate::api::TemperatureReading;
+
+const HWMON_CLASS_PATH: &str = "/sys/class/hwmon";
+
+/// Read all `temp*_input` sensors of all hwmon devices
+pub fn read_temperatures() -> Vec<TemperatureReading> {
+    read_temperatures_in(Path::new(HWMON_CLASS_PATH))
+}
+
+fn read_temperatures_in(class_dir: &Path) -> Vec<TemperatureReading> {
+    let mut readings = Vec::new();
+    let devices = match std::fs::read_dir(class_dir) {
+        Ok(x) => x,
+        Err(e) => {
+            log::warn!("Failed to read hwmon devices in {}: {}", class_dir.display(), e);
+            return readings;
+        }
+    };
+    for device in devices.filter_map(|d| d.ok()) {
+        let dir = device.path();
+        let sensor = read_trimmed(&dir.join("name"))
+            .unwrap_or_else(|| device.file_name().to_string_lossy().into_owned());
+        let entries = if let Ok(x) = std::fs::read_dir(&dir) {
+            x
+        } else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let filename = entry.file_name().to_string_lossy().into_owned();
+            let input = if let Some(x) = filename.strip_suffix("_input") {
+                x
+            } else {
+                continue;
+            };
+            if !input.starts_with("temp") {
+                continue;
+            }
+            // values are in millidegrees Celsius
+            if let Some(millidegrees) =
+                read_trimmed(&entry.path()).and_then(|x| x.parse::<f64>().ok())
+            {
+                readings.push(TemperatureReading {
+                    sensor: sensor.clone(),
+                    input: input.to_owned(),
+                    label: read_trimmed(&dir.join(format!("{}_label", input))),
+                    temperature: millidegrees / 1000.0,
+                });
+            }
+        }
+    }
+    readings.sort_by(|a, b| (&a.sensor, &a.input).cmp(&(&b.sensor, &b.input)));
+    readings
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path).ok().map(|x| x.trim().to_owned())
+}
+
+/// Highest temperature of the sensors whose hwmon name is `sensor` (or all sensors, when it's None)
+pub fn max_temperature(readings: &[TemperatureReading], sensor: Option<&str>) -> Option<f64> {
+    readings
+        .iter()
+        .filter(|r| sensor.map(|s| r.sensor == s).unwrap_or(true))
+        .map(|r| r.temperature)
+        .fold(None, |max: Option<f64>, t| Some(max.map(|m| m.max(t)).unwrap_or(t)))
+}
+
+/// hwmon devices which measure the APU (or CPU and GPU) temperature
+const APU_SENSORS: &[&str] = &["amdgpu", "k10temp", "zenpower", "coretemp"];
+
+/// Hottest APU sensor reading
+pub fn apu_temperature(readings: &[TemperatureReading]) -> Option<f64> {
+    APU_SENSORS
+        .iter()
+        .filter_map(|sensor| max_temperature(readings, Some(sensor)))
+        .fold(None, |max: Option<f64>, t| Some(max.map(|m| m.max(t)).unwrap_or(t)))
+}
+
+/// Battery temperature from the power_supply `temp` attribute (in tenths of a degree Celsius)
+pub fn read_battery_temperature() -> Option<f64> {
+    let supplies = std::fs::read_dir("/sys/class/power_supply").ok()?;
+    supplies
+        .filter_map(|s| s.ok())
+        .map(|s| s.path())
+        .filter(|dir| read_trimmed(&dir.join("type")).as_deref() == Some("Battery"))
+        .filter_map(|dir| read_trimmed(&dir.join("temp")).and_then(|x| x.parse::<f64>().ok()))
+        .map(|decidegrees| decidegrees / 10.0)
+        .next()
+}
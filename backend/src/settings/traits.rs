@@ -55,6 +55,22 @@ pub trait TGpu: OnSet + OnResume + OnPowerEvent + Debug + Send {
 
     fn slow_memory(&mut self) -> &mut bool;
 
+    /// Take the values of `json` (as made by `json()`), keeping what the driver knows about the hardware
+    fn load_json(&mut self, json: crate::persist::GpuJson) {
+        self.ppt(json.fast_ppt, json.slow_ppt);
+        self.clock_limits(
+            json.clock_limits
+                .map(|x| super::min_max_from_json(x, crate::persist::LATEST_VERSION)),
+        );
+        *self.slow_memory() = json.slow_memory;
+    }
+
+    /// Read back what the hardware is using, to spot values which something else changed
+    fn actual(&self) -> crate::api::GpuActual;
+
+    /// Write the desired values again, for the fields of `actual` which drifted
+    fn reapply(&mut self, actual: &crate::api::GpuActual) -> Result<(), Vec<SettingError>>;
+
     fn provider(&self) -> crate::persist::DriverJson {
         crate::persist::DriverJson::AutoDetect
     }
@@ -71,6 +87,12 @@ pub trait TCpus: OnSet + OnResume + OnPowerEvent + Debug + Send {
 
     fn smt(&mut self) -> &'_ mut bool;
 
+    /// Read back what the hardware is using, to spot values which something else changed
+    fn actual(&self) -> crate::api::CpusActual;
+
+    /// Write the desired values again, for the fields of `actual` which drifted
+    fn reapply(&mut self, actual: &crate::api::CpusActual) -> Result<(), Vec<SettingError>>;
+
     fn provider(&self) -> crate::persist::DriverJson {
         crate::persist::DriverJson::AutoDetect
     }
@@ -86,6 +108,8 @@ pub trait TCpu: Debug + Send {
     fn clock_limits(&mut self, limits: Option<MinMax<u64>>);
 
     fn get_clock_limits(&self) -> Option<&MinMax<u64>>;
+
+    fn actual(&self) -> crate::api::CpuActual;
 }
 
 pub trait TGeneral: OnSet + OnResume + OnPowerEvent + Debug + Send {
@@ -103,6 +127,10 @@ pub trait TGeneral: OnSet + OnResume + OnPowerEvent + Debug + Send {
 
     fn name(&mut self, name: String);
 
+    fn get_parent(&self) -> Option<&'_ str>;
+
+    fn parent(&mut self, parent: Option<String>);
+
     fn provider(&self) -> crate::persist::DriverJson;
 
     fn on_event(&self) -> &'_ crate::persist::OnEventJson;
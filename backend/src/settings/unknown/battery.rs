@@ -1,11 +1,13 @@
 use std::convert::Into;
 
 use crate::persist::BatteryJson;
-use crate::settings::TBattery;
+use crate::settings::{PowerMode, TBattery};
 use crate::settings::{OnResume, OnSet, SettingError};
 
-#[derive(Debug, Clone)]
-pub struct Battery;
+#[derive(Debug, Clone, Default)]
+pub struct Battery {
+    state: crate::state::generic::Battery,
+}
 
 impl Into<BatteryJson> for Battery {
     #[inline]
@@ -86,6 +88,14 @@ impl TBattery for Battery {
         None
     }
 
+    fn check_power(&mut self) -> Result<Vec<PowerMode>, Vec<SettingError>> {
+        // charge amount is unknown, but the charger state may still be available
+        let online = crate::settings::util::read_charger_online(&sysfuss::SysPath::default());
+        Ok(crate::settings::util::charger_event(&mut self.state.charger_state, online)
+            .into_iter()
+            .collect())
+    }
+
     fn provider(&self) -> crate::persist::DriverJson {
         crate::persist::DriverJson::Unknown
     }
@@ -2,7 +2,7 @@ use std::convert::Into;
 
 use crate::persist::CpuJson;
 use crate::settings::MinMax;
-use crate::settings::{OnResume, OnSet, SettingError};
+use crate::settings::{OnResume, OnSet, SettingError, SettingErrorKind};
 use crate::settings::{TCpu, TCpus};
 
 const CPU_PRESENT_PATH: &str = "/sys/devices/system/cpu/present";
@@ -25,6 +25,8 @@ impl OnSet for Cpus {
                     .map_err(|e| SettingError {
                         msg: format!("Failed to write `on` to `{}`: {}", CPU_SMT_PATH, e),
                         setting: crate::settings::SettingVariant::Cpu,
+                        kind: SettingErrorKind::of_write(&e),
+                        target: Some(CPU_SMT_PATH.to_owned()),
                     })
                     .unwrap_or_else(|e| {
                         errors.push(e);
@@ -34,6 +36,8 @@ impl OnSet for Cpus {
                     .map_err(|e| SettingError {
                         msg: format!("Failed to write `off` to `{}`: {}", CPU_SMT_PATH, e),
                         setting: crate::settings::SettingVariant::Cpu,
+                        kind: SettingErrorKind::of_write(&e),
+                        target: Some(CPU_SMT_PATH.to_owned()),
                     })
                     .unwrap_or_else(|e| {
                         errors.push(e);
@@ -169,6 +173,18 @@ impl TCpus for Cpus {
         &mut self.smt
     }
 
+    fn actual(&self) -> crate::api::CpusActual {
+        crate::settings::util::cpus_actual(
+            self.smt,
+            self.smt_capable,
+            self.cpus.iter().map(|x| x.actual()).collect(),
+        )
+    }
+
+    fn reapply(&mut self, actual: &crate::api::CpusActual) -> Result<(), Vec<SettingError>> {
+        crate::settings::util::reapply_cpus(actual)
+    }
+
     fn provider(&self) -> crate::persist::DriverJson {
         crate::persist::DriverJson::Unknown
     }
@@ -214,6 +230,8 @@ impl Cpu {
                 .map_err(|e| SettingError {
                     msg: format!("Failed to write to `{}`: {}", &online_path, e),
                     setting: crate::settings::SettingVariant::Cpu,
+                    kind: SettingErrorKind::of_write(&e),
+                    target: Some(online_path.clone()),
                 })
                 .unwrap_or_else(|e| errors.push(e));
         }
@@ -228,6 +246,8 @@ impl Cpu {
                         &self.governor, &governor_path, e
                     ),
                     setting: crate::settings::SettingVariant::Cpu,
+                    kind: SettingErrorKind::of_write(&e),
+                    target: Some(governor_path.clone()),
                 })
                 .unwrap_or_else(|e| errors.push(e));
         }
@@ -305,6 +325,10 @@ impl TCpu for Cpu {
     fn get_clock_limits(&self) -> Option<&MinMax<u64>> {
         None
     }
+
+    fn actual(&self) -> crate::api::CpuActual {
+        crate::settings::util::cpu_actual(self.index, self.online, &self.governor)
+    }
 }
 
 #[inline]
@@ -18,11 +18,176 @@ pub fn always_satisfied<'a, X>(_: &'a X) -> bool {
     true
 }
 
+/// Whether external power is connected, according to the `online` attribute of Mains and USB power supplies.
+/// Falls back to the battery `status` when there are no such power supplies.
+/// None when neither is available.
+pub fn read_charger_online(root: &sysfuss::SysPath) -> Option<bool> {
+    use sysfuss::SysEntityRawExt;
+    let supplies: Vec<sysfuss::PowerSupplyPath> = match root.power_supply(always_satisfied) {
+        Ok(iter) => iter.collect(),
+        Err(e) => {
+            log::debug!("Failed to list power supplies for charger detection: {}", e);
+            return None;
+        }
+    };
+    let mut charger_found = false;
+    let mut battery_status = None;
+    for psu in &supplies {
+        match psu.type_str().as_deref().map(|x| x.trim()) {
+            Ok("Mains") | Ok("USB") => {
+                if let Ok(online) = psu.attribute_str("online") {
+                    charger_found = true;
+                    if online.trim() == "1" {
+                        return Some(true);
+                    }
+                }
+            }
+            Ok("Battery") if battery_status.is_none() => {
+                battery_status = psu.attribute_str("status").ok();
+            }
+            _ => {}
+        }
+    }
+    if charger_found {
+        Some(false)
+    } else {
+        match battery_status.as_deref().map(|x| x.trim()) {
+            Some("Charging") | Some("Full") | Some("Not charging") => Some(true),
+            Some("Discharging") => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Power event for a change in charger state, updating the known state
+pub fn charger_event(
+    state: &mut crate::state::ChargeState,
+    online: Option<bool>,
+) -> Option<super::PowerMode> {
+    use crate::state::ChargeState;
+    match online {
+        Some(true) if *state != ChargeState::PluggedIn => {
+            *state = ChargeState::PluggedIn;
+            Some(super::PowerMode::PluggedIn)
+        }
+        Some(false) if *state != ChargeState::Unplugged => {
+            *state = ChargeState::Unplugged;
+            Some(super::PowerMode::PluggedOut)
+        }
+        _ => None,
+    }
+}
+
 pub const CARD_NEEDS: &[&'static str] = &[
     "dev",
     "uevent"
 ];
 
+/// SMT state according to `smt/control`, None when SMT can't be controlled
+pub fn read_smt() -> Option<bool> {
+    let control = std::fs::read_to_string("/sys/devices/system/cpu/smt/control").ok()?;
+    match control.trim() {
+        "on" => Some(true),
+        "off" | "forceoff" => Some(false),
+        _ => None,
+    }
+}
+
+/// Whether a CPU is online, according to its `online` attribute
+pub fn read_cpu_online(index: usize) -> Option<bool> {
+    match std::fs::read_to_string(format!("/sys/devices/system/cpu/cpu{}/online", index)) {
+        Ok(online) => Some(online.trim() == "1"),
+        // cpu0 usually can't be turned off, so it doesn't have the attribute
+        Err(_) if index == 0 => Some(true),
+        Err(_) => None,
+    }
+}
+
+pub fn read_cpu_governor(index: usize) -> Option<String> {
+    std::fs::read_to_string(format!(
+        "/sys/devices/system/cpu/cpu{}/cpufreq/scaling_governor",
+        index
+    ))
+    .ok()
+    .map(|x| x.trim().to_owned())
+}
+
+/// Online state and governor of a CPU as read back from sysfs, without clock limits
+pub fn cpu_actual(index: usize, online: bool, governor: &str) -> crate::api::CpuActual {
+    use crate::api::ActualValue;
+    let online = online || index == 0;
+    crate::api::CpuActual {
+        online: ActualValue::new(Some(online), read_cpu_online(index)),
+        // offline CPUs have no cpufreq attributes
+        governor: ActualValue::new(
+            Some(governor.to_owned()).filter(|_| online),
+            read_cpu_governor(index),
+        ),
+        clock_min: ActualValue::new(None, None),
+        clock_max: ActualValue::new(None, None),
+    }
+}
+
+/// All CPUs as read back from sysfs.
+/// While SMT is off, odd CPUs are offline regardless of their own settings.
+pub fn cpus_actual(
+    smt: bool,
+    smt_capable: bool,
+    mut cpus: Vec<crate::api::CpuActual>,
+) -> crate::api::CpusActual {
+    use crate::api::ActualValue;
+    if smt_capable && !smt {
+        for cpu in cpus.iter_mut().skip(1).step_by(2) {
+            cpu.online = ActualValue::new(Some(false), cpu.online.actual);
+            cpu.governor = ActualValue::new(None, cpu.governor.actual.take());
+        }
+    }
+    crate::api::CpusActual {
+        smt: ActualValue::new(Some(smt).filter(|_| smt_capable), read_smt()),
+        cpus,
+    }
+}
+
+/// Write the desired SMT state, online state and governor again, but only where they drifted
+pub fn reapply_cpus(actual: &crate::api::CpusActual) -> Result<(), Vec<super::SettingError>> {
+    let mut errors = Vec::new();
+    let mut write = |path: String, value: &str| {
+        usdpl_back::api::files::write_single(&path, value)
+            .map_err(|e| super::SettingError {
+                msg: format!("Failed to write `{}` to `{}`: {}", value, &path, e),
+                setting: super::SettingVariant::Cpu,
+                kind: super::SettingErrorKind::of_write(&e),
+                target: Some(path.clone()),
+            })
+            .unwrap_or_else(|e| errors.push(e));
+    };
+    if let (true, Some(smt)) = (actual.smt.drift, actual.smt.desired) {
+        write(
+            "/sys/devices/system/cpu/smt/control".to_owned(),
+            if smt { "on" } else { "off" },
+        );
+    }
+    for (index, cpu) in actual.cpus.iter().enumerate() {
+        if let (true, Some(online)) = (cpu.online.drift, cpu.online.desired) {
+            write(
+                format!("/sys/devices/system/cpu/cpu{}/online", index),
+                if online { "1" } else { "0" },
+            );
+        }
+        if let (true, Some(governor)) = (cpu.governor.drift, &cpu.governor.desired) {
+            write(
+                format!("/sys/devices/system/cpu/cpu{}/cpufreq/scaling_governor", index),
+                governor,
+            );
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -56,6 +221,25 @@ mod test {
         assert_eq!(guess_smt(&input), false);
     }
 
+    #[test]
+    fn cpus_actual_test() {
+        use crate::api::{ActualValue, CpuActual};
+        let cpu = |online: bool| CpuActual {
+            online: ActualValue::new(Some(true), Some(online)),
+            governor: ActualValue::new(Some("schedutil".to_owned()), Some("performance".to_owned())),
+            clock_min: ActualValue::new(None, Some(1400)),
+            clock_max: ActualValue::new(None, None),
+        };
+        let actual = cpus_actual(false, true, vec![cpu(true), cpu(false)]);
+        assert!(actual.cpus[0].governor.drift);
+        assert!(!actual.cpus[0].online.drift);
+        assert!(!actual.cpus[0].clock_min.drift);
+        // SMT off takes odd CPUs offline
+        assert_eq!(actual.cpus[1].online.desired, Some(false));
+        assert!(!actual.cpus[1].online.drift);
+        assert!(!actual.cpus[1].governor.drift);
+    }
+
     fn cpu_with_online(status: bool) -> CpuJson {
         CpuJson {
             online: status,
@@ -0,0 +1,157 @@
+use std::fmt::Display;
+
+use crate::api::{LimitAction, LimitViolation, RangeLimit};
+
+use super::{MinMax, Settings};
+
+/// Check settings against the limits reported by their drivers.
+/// When `clamp` is true, settings outside of those limits are changed to the closest allowed value
+/// (or unset, when there is no closest value).
+pub fn check_limits(settings: &mut Settings, clamp: bool) -> Vec<LimitViolation> {
+    let mut violations = Vec::new();
+
+    // GPU
+    let limits = settings.gpu.limits();
+    let (fast_ppt, slow_ppt) = settings.gpu.get_ppt();
+    let new_fast_ppt = check_range("gpu.fast_ppt", fast_ppt, &limits.fast_ppt_limits, clamp, &mut violations);
+    let new_slow_ppt = check_range("gpu.slow_ppt", slow_ppt, &limits.slow_ppt_limits, clamp, &mut violations);
+    if new_fast_ppt != fast_ppt || new_slow_ppt != slow_ppt {
+        settings.gpu.ppt(new_fast_ppt, new_slow_ppt);
+    }
+    if let Some(clocks) = settings.gpu.get_clock_limits().cloned() {
+        let new_clocks = check_min_max(
+            "gpu.clock_limits",
+            &clocks,
+            &limits.clock_min_limits,
+            &limits.clock_max_limits,
+            clamp,
+            &mut violations,
+        );
+        if !same_min_max(&new_clocks, &clocks) {
+            settings.gpu.clock_limits(Some(new_clocks));
+        }
+    }
+
+    // CPUs
+    let limits = settings.cpus.limits();
+    for (i, cpu) in settings.cpus.cpus().into_iter().enumerate() {
+        let cpu_limits = if let Some(x) = limits.cpus.get(i) {
+            x
+        } else {
+            continue;
+        };
+        if let Some(clocks) = cpu.get_clock_limits().cloned() {
+            let new_clocks = check_min_max(
+                &format!("cpus.{}.clock_limits", i),
+                &clocks,
+                &cpu_limits.clock_min_limits,
+                &cpu_limits.clock_max_limits,
+                clamp,
+                &mut violations,
+            );
+            if !same_min_max(&new_clocks, &clocks) {
+                cpu.clock_limits(Some(new_clocks));
+            }
+        }
+        let governor = cpu.get_governor();
+        if !cpu_limits.governors.is_empty()
+            && !cpu_limits.governors.iter().any(|g| g == governor)
+        {
+            // there's no sensible closest governor, so leave it for the driver to reject
+            violations.push(LimitViolation {
+                setting: format!("cpus.{}.governor", i),
+                value: governor.to_owned(),
+                allowed: format!("one of {}", cpu_limits.governors.join(", ")),
+                action: LimitAction::Kept,
+                new_value: None,
+            });
+        }
+    }
+
+    // battery
+    let limits = settings.battery.limits();
+    let charge_rate = settings.battery.get_charge_rate();
+    let new_charge_rate = check_range(
+        "battery.charge_rate",
+        charge_rate,
+        &limits.charge_current,
+        clamp,
+        &mut violations,
+    );
+    if new_charge_rate != charge_rate {
+        settings.battery.charge_rate(new_charge_rate);
+    }
+    if let Some(mode) = settings.battery.get_charge_mode() {
+        if !limits.charge_modes.contains(&mode) {
+            violations.push(LimitViolation {
+                setting: "battery.charge_mode".to_owned(),
+                value: mode,
+                allowed: format!("one of {}", limits.charge_modes.join(", ")),
+                action: if clamp { LimitAction::Dropped } else { LimitAction::Kept },
+                new_value: None,
+            });
+            if clamp {
+                settings.battery.charge_mode(None);
+            }
+        }
+    }
+    let charge_limit = settings.battery.get_charge_limit();
+    let new_charge_limit = check_range(
+        "battery.charge_limit",
+        charge_limit,
+        &limits.charge_limit,
+        clamp,
+        &mut violations,
+    );
+    if new_charge_limit != charge_limit {
+        settings.battery.charge_limit(new_charge_limit);
+    }
+
+    violations
+}
+
+/// Returns the value, clamped to be within `limit` when `clamp` is true
+fn check_range<T: PartialOrd + Copy + Display>(
+    setting: &str,
+    value: Option<T>,
+    limit: &Option<RangeLimit<T>>,
+    clamp: bool,
+    violations: &mut Vec<LimitViolation>,
+) -> Option<T> {
+    match (value, limit) {
+        (Some(val), Some(limit)) if val < limit.min || val > limit.max => {
+            let clamped = if val < limit.min { limit.min } else { limit.max };
+            violations.push(LimitViolation {
+                setting: setting.to_owned(),
+                value: val.to_string(),
+                allowed: format!("{} to {}", limit.min, limit.max),
+                action: if clamp { LimitAction::Clamped } else { LimitAction::Kept },
+                new_value: if clamp { Some(clamped.to_string()) } else { None },
+            });
+            if clamp {
+                Some(clamped)
+            } else {
+                value
+            }
+        }
+        _ => value,
+    }
+}
+
+fn check_min_max(
+    setting: &str,
+    value: &MinMax<u64>,
+    min_limit: &Option<RangeLimit<u64>>,
+    max_limit: &Option<RangeLimit<u64>>,
+    clamp: bool,
+    violations: &mut Vec<LimitViolation>,
+) -> MinMax<u64> {
+    MinMax {
+        min: check_range(&format!("{}.min", setting), value.min, min_limit, clamp, violations),
+        max: check_range(&format!("{}.max", setting), value.max, max_limit, clamp, violations),
+    }
+}
+
+fn same_min_max(a: &MinMax<u64>, b: &MinMax<u64>) -> bool {
+    a.min == b.min && a.max == b.max
+}
@@ -0,0 +1,59 @@
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+
+use crate::api::handler::ApiMessage;
+use crate::settings::OriginalState;
+
+/// How long the api worker gets to restore the original state before it's done without it
+const EXIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Trap SIGTERM and SIGINT (i.e. when the plugin is unloaded), and put the hardware back
+/// the way it was in `original` before exiting.
+pub fn spawn(sender: Sender<ApiMessage>, original: OriginalState) -> JoinHandle<()> {
+    let original = Arc::new(original);
+    let signals = Signals::new([SIGTERM, SIGINT]);
+    if let Err(e) = &signals {
+        log::error!(
+            "Failed to trap signals, original state will not be restored on exit: {}",
+            e
+        );
+    }
+    thread::spawn(move || {
+        log::info!("signal_worker starting...");
+        let mut signals = match signals {
+            Ok(signals) => signals,
+            Err(_) => return,
+        };
+        let signal = match signals.forever().next() {
+            Some(signal) => signal,
+            None => return,
+        };
+        log::info!("Received signal {}, restoring original state before exiting", signal);
+        // the api worker applies everything, so restoring there can't race with other changes
+        let (done_tx, done_rx) = mpsc::channel();
+        let exit = ApiMessage::Exit(
+            original.clone(),
+            Box::new(move |()| done_tx.send(()).unwrap_or(())),
+        );
+        if sender.send(exit).is_ok() {
+            match done_rx.recv_timeout(EXIT_TIMEOUT) {
+                Ok(()) => std::process::exit(0),
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    log::error!("api_worker did not exit in time, restoring original state without the drivers")
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    log::error!("api_worker stopped before exiting, restoring original state without the drivers")
+                }
+            }
+        }
+        if let Err(e) = original.restore() {
+            e.iter().for_each(|e| log::error!("Restore error: {}", e));
+        }
+        std::process::exit(1);
+    })
+}
@@ -0,0 +1,14 @@
+use crate::state::ChargeState;
+
+#[derive(Debug, Clone)]
+pub struct Battery {
+    pub charger_state: ChargeState,
+}
+
+impl std::default::Default for Battery {
+    fn default() -> Self {
+        Self {
+            charger_state: ChargeState::Unknown,
+        }
+    }
+}
@@ -1,3 +1,5 @@
+mod battery;
 mod gpu;
 
+pub use battery::Battery;
 pub use gpu::Gpu;
@@ -5,4 +5,5 @@ pub mod generic;
 pub mod steam_deck;
 
 pub use error::StateError;
+pub use steam_deck::ChargeState;
 pub use traits::OnPoll;
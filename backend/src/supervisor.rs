@@ -0,0 +1,192 @@
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::api::{WorkerHealth, WorkerStatus};
+
+const CHECK_PERIOD: Duration = Duration::from_secs(1);
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+/// A worker which ran for this long before crashing starts over with `MIN_BACKOFF`
+const STABLE_PERIOD: Duration = Duration::from_secs(60);
+
+/// What to do when a worker thread finishes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Restart {
+    /// Restart only when the worker panicked (some workers stop on purpose, e.g. to fall back to polling)
+    OnCrash,
+    /// Only report the worker's health (it can't be started again)
+    Never,
+}
+
+struct Worker {
+    restart: Restart,
+    start: Option<Box<dyn FnMut() -> JoinHandle<()> + Send>>,
+    handle: Option<JoinHandle<()>>,
+    started: Instant,
+    backoff: Duration,
+    restart_at: Option<Instant>,
+    /// Index into the shared health
+    index: usize,
+}
+
+/// Keeps an eye on worker threads, restarting them (with backoff) when they crash
+pub struct Supervisor {
+    workers: Vec<Worker>,
+    health: Arc<Mutex<Vec<WorkerHealth>>>,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Self {
+            workers: Vec::new(),
+            health: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Start a worker, which is started again by calling `start` according to `restart`
+    pub fn add(
+        &mut self,
+        name: &'static str,
+        restart: Restart,
+        mut start: impl FnMut() -> JoinHandle<()> + Send + 'static,
+    ) {
+        let handle = start();
+        self.push(name, restart, Some(Box::new(start)), handle);
+    }
+
+    /// Monitor an already started worker, which can't be restarted
+    pub fn watch(&mut self, name: &'static str, handle: JoinHandle<()>) {
+        self.push(name, Restart::Never, None, handle);
+    }
+
+    fn push(
+        &mut self,
+        name: &'static str,
+        restart: Restart,
+        start: Option<Box<dyn FnMut() -> JoinHandle<()> + Send>>,
+        handle: JoinHandle<()>,
+    ) {
+        let mut health = self.health.lock().unwrap();
+        self.workers.push(Worker {
+            restart,
+            start,
+            handle: Some(handle),
+            started: Instant::now(),
+            backoff: MIN_BACKOFF,
+            restart_at: None,
+            index: health.len(),
+        });
+        health.push(WorkerHealth {
+            name: name.to_owned(),
+            status: WorkerStatus::Running,
+            restarts: 0,
+            last_error: None,
+        });
+    }
+
+    /// Current health of all workers, shared with the supervisor thread
+    pub fn health(&self) -> Arc<Mutex<Vec<WorkerHealth>>> {
+        self.health.clone()
+    }
+
+    pub fn spawn(mut self) -> JoinHandle<()> {
+        thread::spawn(move || {
+            log::info!("supervisor starting...");
+            loop {
+                thread::sleep(CHECK_PERIOD);
+                for worker in self.workers.iter_mut() {
+                    let mut health = self.health.lock().unwrap();
+                    check(worker, &mut health[worker.index]);
+                }
+            }
+        })
+    }
+}
+
+fn check(worker: &mut Worker, health: &mut WorkerHealth) {
+    if let Some(restart_at) = worker.restart_at {
+        if Instant::now() >= restart_at {
+            if let Some(start) = &mut worker.start {
+                log::info!("Restarting {} (restart #{})", health.name, health.restarts + 1);
+                worker.handle = Some(start());
+                worker.started = Instant::now();
+                worker.restart_at = None;
+                health.restarts += 1;
+                health.status = WorkerStatus::Running;
+            }
+        }
+        return;
+    }
+    let finished = worker.handle.as_ref().map(|h| h.is_finished()).unwrap_or(false);
+    if !finished {
+        return;
+    }
+    let crashed = match worker.handle.take().unwrap().join() {
+        Ok(()) => {
+            log::info!("{} stopped", health.name);
+            health.status = WorkerStatus::Stopped;
+            false
+        }
+        Err(panic) => {
+            let msg = panic_message(panic.as_ref());
+            log::error!("{} crashed: {}", health.name, msg);
+            health.status = WorkerStatus::Crashed;
+            health.last_error = Some(msg);
+            true
+        }
+    };
+    let restart = match worker.restart {
+        Restart::OnCrash => crashed,
+        Restart::Never => false,
+    };
+    if restart && worker.start.is_some() {
+        if worker.started.elapsed() >= STABLE_PERIOD {
+            worker.backoff = MIN_BACKOFF;
+        }
+        log::info!("Restarting {} in {}s", health.name, worker.backoff.as_secs());
+        worker.restart_at = Some(Instant::now() + worker.backoff);
+        worker.backoff = (worker.backoff * 2).min(MAX_BACKOFF);
+        health.status = WorkerStatus::Restarting;
+    }
+}
+
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    if let Some(msg) = panic.downcast_ref::<&str>() {
+        (*msg).to_owned()
+    } else if let Some(msg) = panic.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crash_restart_test() {
+        let mut supervisor = Supervisor::new();
+        supervisor.add("test_worker", Restart::OnCrash, || {
+            thread::spawn(|| panic!("test_worker send failed"))
+        });
+        let health = supervisor.health();
+        let worker = &mut supervisor.workers[0];
+        while !worker.handle.as_ref().unwrap().is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        check(worker, &mut health.lock().unwrap()[0]);
+        {
+            let health = health.lock().unwrap();
+            assert_eq!(health[0].status, WorkerStatus::Restarting);
+            assert_eq!(health[0].last_error.as_deref(), Some("test_worker send failed"));
+        }
+        assert_eq!(worker.backoff, MIN_BACKOFF * 2);
+        worker.restart_at = Some(Instant::now());
+        check(worker, &mut health.lock().unwrap()[0]);
+        let health = health.lock().unwrap();
+        assert_eq!(health[0].status, WorkerStatus::Running);
+        assert_eq!(health[0].restarts, 1);
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use netlink_sys::{protocols::NETLINK_KOBJECT_UEVENT, Socket, SocketAddr};
+
+use crate::api::handler::ApiMessage;
+
+/// Kernel uevents (as opposed to events re-broadcast by udevd)
+const KERNEL_GROUP: u32 = 1;
+/// Smallest change in battery charge which is reported
+const MIN_CHARGE_CHANGE: f64 = 0.01;
+
+/// Listen for power supply uevents from the kernel, and send power events as soon as they happen.
+/// While this is running, `live` is set and power_worker only polls battery charge, as a backstop.
+/// If the netlink socket can't be used, this stops and polling takes over.
+pub fn spawn(sender: Sender<ApiMessage>, live: Arc<AtomicBool>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        log::info!("uevent_worker starting...");
+        let socket = match open_socket() {
+            Ok(x) => x,
+            Err(e) => {
+                log::warn!("uevent_worker cannot listen for uevents, falling back to polling: {}", e);
+                return;
+            }
+        };
+        log::info!("Power events from uevents started");
+        live.store(true, Ordering::SeqCst);
+        let mut supplies = PowerSupplies::new(crate::settings::read_charger_online(
+            &sysfuss::SysPath::default(),
+        ));
+        let mut buf = Vec::with_capacity(8192);
+        loop {
+            buf.clear();
+            if let Err(e) = socket.recv(&mut buf, 0) {
+                if e.kind() == std::io::ErrorKind::Interrupted {
+                    continue;
+                }
+                // e.g. ENOBUFS when events came in faster than they were read; re-sync with polling
+                log::error!("uevent_worker failed to receive uevent, falling back to polling: {}", e);
+                break;
+            }
+            let event = if let Some(x) = parse_uevent(&buf) {
+                x
+            } else {
+                continue;
+            };
+            if event.get("SUBSYSTEM").map(|x| x.as_str()) != Some("power_supply") {
+                continue;
+            }
+            log::debug!("Power supply uevent: {:?}", event);
+            if supplies.update(&event).into_iter().any(|message| sender.send(message).is_err()) {
+                log::info!("uevent_worker stopping, api_worker is gone");
+                break;
+            }
+        }
+        live.store(false, Ordering::SeqCst);
+        log::info!("Power events from uevents stopped");
+        log::warn!("uevent_worker completed!");
+    })
+}
+
+fn open_socket() -> std::io::Result<Socket> {
+    let mut socket = Socket::new(NETLINK_KOBJECT_UEVENT)?;
+    socket.bind(&SocketAddr::new(0, KERNEL_GROUP))?;
+    Ok(socket)
+}
+
+/// Variables of a kernel uevent, which looks like `action@devpath\0KEY=value\0...`
+fn parse_uevent(message: &[u8]) -> Option<HashMap<String, String>> {
+    let mut parts = message.split(|b| *b == 0);
+    // the header is repeated in the variables
+    if !parts.next()?.contains(&b'@') {
+        return None;
+    }
+    Some(
+        parts
+            .filter_map(|part| std::str::from_utf8(part).ok()?.split_once('='))
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+            .collect(),
+    )
+}
+
+/// What is known about power supplies, from uevents
+struct PowerSupplies {
+    /// Online state of each charger (Mains and USB power supplies)
+    chargers: HashMap<String, bool>,
+    plugged_in: Option<bool>,
+    charge: Option<f64>,
+}
+
+impl PowerSupplies {
+    fn new(plugged_in: Option<bool>) -> Self {
+        Self {
+            chargers: HashMap::new(),
+            plugged_in,
+            charge: None,
+        }
+    }
+
+    /// Power events for a power supply uevent
+    fn update(&mut self, event: &HashMap<String, String>) -> Vec<ApiMessage> {
+        let get = |key: &str| event.get(key).map(|x| x.trim());
+        let get_f64 = |key: &str| get(key).and_then(|x| x.parse::<f64>().ok());
+        let mut messages = Vec::new();
+        let name = get("POWER_SUPPLY_NAME").unwrap_or_default().to_owned();
+        let mut plugged_in = None;
+        match get("POWER_SUPPLY_TYPE") {
+            Some("Mains") | Some("USB") => {
+                if let Some(online) = get("POWER_SUPPLY_ONLINE") {
+                    self.chargers.insert(name, online == "1");
+                    plugged_in = Some(self.chargers.values().any(|x| *x));
+                }
+            }
+            Some("Battery") => {
+                // like read_charger_online(), battery status is only used when there are no chargers
+                if self.chargers.is_empty() {
+                    plugged_in = match get("POWER_SUPPLY_STATUS") {
+                        Some("Charging") | Some("Full") | Some("Not charging") => Some(true),
+                        Some("Discharging") => Some(false),
+                        _ => None,
+                    };
+                }
+                let charge = match (get_f64("POWER_SUPPLY_CHARGE_NOW"), get_f64("POWER_SUPPLY_CHARGE_FULL")) {
+                    (Some(now), Some(full)) if full > 0.0 => Some(now / full),
+                    _ => match (get_f64("POWER_SUPPLY_ENERGY_NOW"), get_f64("POWER_SUPPLY_ENERGY_FULL")) {
+                        (Some(now), Some(full)) if full > 0.0 => Some(now / full),
+                        _ => get_f64("POWER_SUPPLY_CAPACITY").map(|x| x / 100.0),
+                    },
+                };
+                if let Some(charge) = charge {
+                    if self.charge.map(|x| (x - charge).abs() >= MIN_CHARGE_CHANGE).unwrap_or(true) {
+                        self.charge = Some(charge);
+                        messages.push(ApiMessage::OnChargeChange(charge));
+                    }
+                }
+            }
+            _ => {}
+        }
+        if let Some(plugged_in) = plugged_in {
+            if self.plugged_in != Some(plugged_in) {
+                self.plugged_in = Some(plugged_in);
+                // plug events go first, so that charge rules are checked with the new charger state
+                messages.insert(
+                    0,
+                    if plugged_in {
+                        ApiMessage::OnPluggedIn
+                    } else {
+                        ApiMessage::OnUnplugged
+                    },
+                );
+            }
+        }
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power_supply_uevent_test() {
+        let message = b"change@/devices/platform/ACPI0003:00/power_supply/ACAD\0ACTION=change\0SUBSYSTEM=power_supply\0POWER_SUPPLY_NAME=ACAD\0POWER_SUPPLY_TYPE=Mains\0POWER_SUPPLY_ONLINE=1\0SEQNUM=4242\0";
+        let event = parse_uevent(message).unwrap();
+        assert_eq!(event.get("SUBSYSTEM").unwrap(), "power_supply");
+        let mut supplies = PowerSupplies::new(Some(false));
+        let messages = supplies.update(&event);
+        assert!(matches!(messages.as_slice(), [ApiMessage::OnPluggedIn]));
+        assert!(supplies.update(&event).is_empty());
+
+        let message = b"change@/devices/BAT1\0SUBSYSTEM=power_supply\0POWER_SUPPLY_NAME=BAT1\0POWER_SUPPLY_TYPE=Battery\0POWER_SUPPLY_STATUS=Charging\0POWER_SUPPLY_CHARGE_NOW=2500000\0POWER_SUPPLY_CHARGE_FULL=5000000\0";
+        let messages = supplies.update(&parse_uevent(message).unwrap());
+        assert!(matches!(messages.as_slice(), [ApiMessage::OnChargeChange(x)] if (*x - 0.5).abs() < 1e-9));
+        assert!(parse_uevent(b"libudev\0\xfe\xed").is_none());
+    }
+}
@@ -1,19 +1,8 @@
-use std::fmt::Display;
 //use std::sync::{LockResult, MutexGuard};
 //use std::fs::{Permissions, metadata};
 use std::io::{Read, Write};
 use std::os::unix::fs::PermissionsExt;
 
-pub fn unwrap_maybe_fatal<T: Sized, E: Display>(result: Result<T, E>, message: &str) -> T {
-    match result {
-        Ok(x) => x,
-        Err(e) => {
-            log::error!("{}: {}", message, e);
-            panic!("{}: {}", message, e);
-        }
-    }
-}
-
 /*pub fn unwrap_lock<'a, T: Sized>(
     result: LockResult<MutexGuard<'a, T>>,
     lock_name: &str,
@@ -101,3 +90,64 @@ pub fn read_version_file() -> String {
         }
     }
 }
+
+fn applying_sentinel_filepath() -> std::path::PathBuf {
+    settings_dir().join(crate::consts::APPLYING_SENTINEL_FILE)
+}
+
+/// Whether settings were being applied last time the back-end ran, and it never got to confirm that the system was fine afterwards
+pub fn applying_sentinel_exists() -> bool {
+    applying_sentinel_filepath().exists()
+}
+
+/// Mark that settings are about to be applied.
+/// This is synced to disk right away, since the system may not survive long enough to do it later.
+pub fn create_applying_sentinel(settings_file: &std::path::Path) -> std::io::Result<()> {
+    let path = applying_sentinel_filepath();
+    if let Some(parent_dir) = path.parent() {
+        std::fs::create_dir_all(parent_dir)?;
+    }
+    let mut file = std::fs::File::create(&path)?;
+    file.write_all(settings_file.to_string_lossy().as_bytes())?;
+    file.sync_all()
+}
+
+pub fn remove_applying_sentinel() -> std::io::Result<()> {
+    match std::fs::remove_file(applying_sentinel_filepath()) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn recovery_marker_filepath() -> std::path::PathBuf {
+    settings_dir().join(crate::consts::RECOVERY_FILE)
+}
+
+/// Whether system defaults are still in use after an earlier failed startup
+pub fn recovery_marker_exists() -> bool {
+    recovery_marker_filepath().exists()
+}
+
+/// Mark that system defaults are in use after a failed startup, so that a later boot keeps them too.
+/// This is synced to disk right away, like the applying sentinel which it replaces.
+pub fn create_recovery_marker() -> std::io::Result<()> {
+    let path = recovery_marker_filepath();
+    if let Some(parent_dir) = path.parent() {
+        std::fs::create_dir_all(parent_dir)?;
+    }
+    std::fs::File::create(&path)?.sync_all()
+}
+
+/// Stop recovering from a failed startup, if that's still going on
+pub fn end_recovery(recovery: &std::sync::atomic::AtomicBool) {
+    if !recovery.swap(false, std::sync::atomic::Ordering::SeqCst) {
+        return;
+    }
+    log::info!("Done recovering from a failed startup");
+    match std::fs::remove_file(recovery_marker_filepath()) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            log::error!("Failed to remove recovery marker: {}", e)
+        }
+        _ => {}
+    }
+}
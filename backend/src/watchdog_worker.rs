@@ -0,0 +1,19 @@
+use std::thread::{self, JoinHandle};
+
+use crate::consts::STARTUP_HEALTHY_PERIOD;
+
+/// Clear the applying sentinel once the system has survived the startup settings for a while
+pub fn spawn() -> JoinHandle<()> {
+    thread::spawn(move || {
+        log::info!("watchdog_worker starting...");
+        thread::sleep(STARTUP_HEALTHY_PERIOD);
+        match crate::utility::remove_applying_sentinel() {
+            Ok(()) => log::info!(
+                "System stayed healthy for {}s after applying settings",
+                STARTUP_HEALTHY_PERIOD.as_secs()
+            ),
+            Err(e) => log::error!("Failed to remove applying sentinel: {}", e),
+        }
+        log::info!("watchdog_worker completed!");
+    })
+}
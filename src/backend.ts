@@ -203,6 +203,30 @@ export async function getCpusGovernor(): Promise<string[]> {
     return (await call_backend("CPU_get_governors", [])); // -> governors for all CPUs
 }
 
+// a value read back from the hardware, next to what the settings want (null when left to the system or unreadable)
+export type ActualValue<T> = {
+    desired: T | null,
+    actual: T | null,
+    // something other than PowerTools changed the value
+    drift: boolean,
+};
+
+export type CpuActual = {
+    online: ActualValue<boolean>,
+    governor: ActualValue<string>,
+    clock_min: ActualValue<number>,
+    clock_max: ActualValue<number>,
+};
+
+export type CpusActual = {
+    smt: ActualValue<boolean>,
+    cpus: CpuActual[],
+};
+
+export async function getCpusActual(): Promise<CpusActual> {
+    return (await call_backend("CPU_get_actual", []))[0];
+}
+
 // GPU
 
 export async function setGpuPpt(fast: number, slow: number): Promise<number[]> {
@@ -237,6 +261,18 @@ export async function getGpuSlowMemory(): Promise<boolean> {
     return (await call_backend("GPU_get_slow_memory", []))[0];
 }
 
+export type GpuActual = {
+    fast_ppt: ActualValue<number>,
+    slow_ppt: ActualValue<number>,
+    clock_min: ActualValue<number>,
+    clock_max: ActualValue<number>,
+    performance_level: ActualValue<string>,
+};
+
+export async function getGpuActual(): Promise<GpuActual> {
+    return (await call_backend("GPU_get_actual", []))[0];
+}
+
 // general
 
 export async function setGeneralPersistent(val: boolean): Promise<boolean> {
@@ -271,10 +307,152 @@ export async function waitForComplete(): Promise<boolean> {
     return (await call_backend("GENERAL_wait_for_unlocks", []))[0];
 }
 
+export type ApplyError = {
+    /// e.g. "on_set" or "on_power_event"
+    source: string,
+    setting: "Battery" | "Cpu" | "Gpu" | "General",
+    /// for picking a (localized) message
+    kind: "io" | "permission_denied" | "not_found" | "parse" | "unsupported" | "out_of_range" | "backend",
+    /// sysfs path or parameter which the error is about
+    target: string | null,
+    msg: string,
+    /// seconds since the Unix epoch
+    time: number,
+};
+
+/// Call after setters to wait until their changes are applied, getting any errors from that
+export async function waitForApply(): Promise<ApplyError[]> {
+    return (await call_backend("GENERAL_wait_for_apply", []));
+}
+
+export async function getErrors(): Promise<ApplyError[]> {
+    return (await call_backend("GENERAL_get_errors", []));
+}
+
 export async function getLimits(): Promise<SettingsLimits> {
     return (await call_backend("GENERAL_get_limits", []))[0];
 }
 
+export async function getLimitViolations(): Promise<LimitViolation[]> {
+    return (await call_backend("GENERAL_get_limit_violations", []));
+}
+
+export type PowerRule = {
+    /// "plug-in", "plug-out", ">80", "<20", "temp>85", etc.
+    trigger: string,
+    /// hwmon device name for temperature triggers; the hottest sensor when unset
+    sensor?: string,
+    /// degrees Celsius to cool down by before a temperature rule is undone (default 5)
+    hysteresis?: number,
+    fast_ppt?: number,
+    slow_ppt?: number,
+    gpu_clock_limits?: { min: number | null, max: number | null },
+    cpus_online?: boolean[],
+    governor?: string,
+    smt?: boolean,
+    charge_rate?: number,
+    charge_mode?: string,
+    charge_limit?: number,
+};
+
+export async function setPowerRules(rules: PowerRule[]): Promise<boolean | string> {
+    return (await call_backend("GENERAL_set_rules", [JSON.stringify(rules)]))[0];
+}
+
+export async function getPowerRules(): Promise<PowerRule[]> {
+    return (await call_backend("GENERAL_get_rules", []))[0];
+}
+
+// periodically re-apply settings which something else changed (per profile)
+export async function setEnforce(val: boolean): Promise<boolean> {
+    return (await call_backend("GENERAL_set_enforce", [val]))[0];
+}
+
+export async function getEnforce(): Promise<boolean> {
+    return (await call_backend("GENERAL_get_enforce", []))[0];
+}
+
+export type ScheduleEntry = {
+    /// local time of day, like "22:30"
+    start: string,
+    /// days of the week (0 = Sunday); every day when empty
+    days?: number[],
+    /// profile to load: the main profile, or a game's profile by app id
+    /// (with the name to use in case it doesn't exist yet)
+    profile?: "main" | { game: { id: number, name: string } },
+    /// changes to apply after loading the profile
+    settings?: Omit<PowerRule, "trigger" | "sensor" | "hysteresis">,
+};
+
+export type Schedule = {
+    version: number,
+    entries: ScheduleEntry[],
+};
+
+export async function getSchedule(): Promise<Schedule | string> {
+    return (await call_backend("GENERAL_get_schedule", []))[0];
+}
+
+export async function setSchedule(schedule: Schedule): Promise<boolean | string> {
+    return (await call_backend("GENERAL_set_schedule", [JSON.stringify(schedule)]))[0];
+}
+
+// app id of the game detected by the backend, or null when no game is running
+export async function getRunningGame(): Promise<string | null> {
+    return (await call_backend("GENERAL_get_running_game", []))[0];
+}
+
+export type TemperatureReading = {
+    /// hwmon device name, e.g. "amdgpu"
+    sensor: string,
+    /// e.g. "temp1"
+    input: string,
+    label: string | null,
+    /// degrees Celsius
+    temperature: number,
+};
+
+export async function getTemperatures(): Promise<TemperatureReading[]> {
+    return (await call_backend("GENERAL_get_temperatures", []));
+}
+
+export type ThermalGovernorConfig = {
+    enabled: boolean,
+    /// degrees Celsius
+    apu_ceiling: number,
+    battery_ceiling: number,
+    hysteresis: number,
+};
+
+export type ThermalStatus = {
+    config: ThermalGovernorConfig,
+    level: number,
+    max_level: number,
+    throttling: boolean,
+    apu_temperature: number | null,
+    battery_temperature: number | null,
+};
+
+export async function getThermalStatus(): Promise<ThermalStatus> {
+    return (await call_backend("GENERAL_get_thermal_status", []))[0];
+}
+
+export async function setThermalGovernor(config: ThermalGovernorConfig): Promise<boolean> {
+    return (await call_backend("GENERAL_set_thermal_governor", [config]))[0];
+}
+
+export type WorkerHealth = {
+    name: string,
+    status: "running" | "stopped" | "crashed" | "restarting",
+    restarts: number,
+    /// panic message from the last crash
+    last_error: string | null,
+};
+
+export async function getHealth(): Promise<WorkerHealth[]> {
+    return (await call_backend("GENERAL_get_health", []));
+}
+
 export async function getDriverProviderName(name: string): Promise<string> {
     return (await call_backend("GENERAL_get_provider", [name]))[0];
 }
@@ -322,7 +500,7 @@ export async function getMessages(since: number | null): Promise<Message[]> {
     return (await call_backend("MESSAGE_get", [since]));
 }
 
-export async function dismissMessage(id: number): Promise<boolean> {
+export async function dismissMessage(id: number | null): Promise<boolean> {
     return (await call_backend("MESSAGE_dismiss", [id]))[0];
 }
 
@@ -344,3 +522,113 @@ export async function getPeriodicals(): Promise<Periodicals> {
         settings_path: result[4],
     };
 }
+
+export type ProfileInfo = {
+    id: number | null,
+    filename: string,
+    name: string,
+    driver: string | null,
+    persistent: boolean,
+    active: boolean,
+    /// Filename of the profile this one inherits from
+    parent: string | null,
+    /// Last modified time, in seconds since the UNIX epoch
+    modified: number | null,
+};
+
+export async function getProfiles(): Promise<ProfileInfo[]> {
+    return (await call_backend("GENERAL_get_profiles", []));
+}
+
+export async function renameProfile(id: string, name: string): Promise<boolean | string> {
+    return (await call_backend("GENERAL_rename_profile", [id, name]))[0];
+}
+
+export async function duplicateProfile(src_id: string, dst_id: string): Promise<boolean | string> {
+    return (await call_backend("GENERAL_duplicate_profile", [src_id, dst_id]))[0];
+}
+
+export async function deleteProfile(id: string): Promise<boolean | string> {
+    return (await call_backend("GENERAL_delete_profile", [id]))[0];
+}
+
+export type ProfileLayers = {
+    /// Filename of the profile the current settings inherit from
+    parent: string | null,
+    /// Settings which differ from the parent (e.g. "gpu.fast_ppt" or "cpus.0.online")
+    overridden: string[],
+    /// Settings which are the same as the parent's
+    inherited: string[],
+};
+
+export async function setProfileParent(parent: string | null): Promise<boolean | string> {
+    return (await call_backend("GENERAL_set_parent", [parent]))[0];
+}
+
+export async function getProfileLayers(): Promise<ProfileLayers | string> {
+    return (await call_backend("GENERAL_get_layers", []))[0];
+}
+
+export type HistoryEntry = {
+    /// Time of the change, in seconds since the UNIX epoch
+    time: number,
+    /// Settings which changed compared to the previous entry
+    changed: string[],
+    /// Whether this entry matches the current settings
+    current: boolean,
+};
+
+export type SettingsHistory = {
+    position: number,
+    can_undo: boolean,
+    can_redo: boolean,
+    /// Oldest entry first
+    entries: HistoryEntry[],
+};
+
+export async function undoSettings(): Promise<boolean> {
+    return (await call_backend("GENERAL_undo", []))[0];
+}
+
+export async function redoSettings(): Promise<boolean> {
+    return (await call_backend("GENERAL_redo", []))[0];
+}
+
+export async function getSettingsHistory(): Promise<SettingsHistory> {
+    return (await call_backend("GENERAL_history", []))[0];
+}
+
+export type LimitViolation = {
+    /// Path of the setting (e.g. "gpu.fast_ppt" or "cpus.0.clock_limits.max")
+    setting: string,
+    value: string,
+    /// Description of the allowed values
+    allowed: string,
+    action: "clamped" | "dropped" | "kept",
+    /// Value the setting was changed to, if it was clamped
+    new_value: string | null,
+};
+
+export type ImportedProfile = {
+    filename: string,
+    name: string,
+    /// Settings which were changed to fit within this device's limits
+    clamped: LimitViolation[],
+};
+
+export type ImportResult = {
+    imported: ImportedProfile[],
+    /// Whether the profiles were exported from the same kind of device
+    fingerprint_match: boolean,
+    warnings: string[],
+};
+
+// exports all saved profiles when no filenames are provided
+export async function exportProfiles(filenames: string[]): Promise<any | string> {
+    return (await call_backend("GENERAL_export_profiles", filenames))[0];
+}
+
+// fails without importing anything if a profile already exists, unless overwrite is set
+export async function importProfiles(bundle: string, overwrite: boolean = false): Promise<ImportResult | string> {
+    return (await call_backend("GENERAL_import_profiles", [bundle, overwrite]))[0];
+}
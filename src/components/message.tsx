@@ -38,9 +38,7 @@ export class DevMessages extends Component<backend.IdcProps> {
                     <ButtonItem
                         layout="below"
                         onClick={(_: MouseEvent) => {
-                            if (message.id) {
-                                backend.dismissMessage(message.id);
-                            }
+                            backend.dismissMessage(message.id);
                             messages.shift();
                             set_value(MESSAGE_LIST, messages);
                             reloadGUI("MessageDismissed");